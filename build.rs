@@ -0,0 +1,29 @@
+use std::process::Command;
+
+// Embeds the commit the binary was built from so run manifests can record it.
+fn main() {
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+
+    println!(
+        "cargo:rustc-env=THESIS_GIT_COMMIT={}{}",
+        commit,
+        if dirty { "-dirty" } else { "" }
+    );
+    // Sources are watched too, so that editing a file without staging it
+    // still marks the build dirty.
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
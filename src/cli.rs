@@ -0,0 +1,108 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::utils::Override;
+
+/// Agent-based hawk-dove simulation on a learned visiting network.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Directory config and score files are read from.
+    #[arg(
+        long = "input",
+        value_name = "DIR",
+        global = true,
+        env = "THESIS_INPUT",
+        default_value = "Input"
+    )]
+    pub input_root: PathBuf,
+
+    /// Directory results are written to, and plots read from and drawn into.
+    #[arg(
+        long = "output",
+        value_name = "DIR",
+        global = true,
+        env = "THESIS_OUTPUT",
+        default_value = "Output"
+    )]
+    pub output_root: PathBuf,
+
+    /// Replace a single config key, e.g. `--set agent_parameters.net_tremble=0.05`;
+    /// may be repeated. Applied after `THESIS__SECTION__KEY` environment variables.
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub set: Vec<Override>,
+
+    /// Number of worker threads (defaults to one per core).
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Print more progress; repeat for more detail.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only print errors.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run every config file in a directory of the input root, or a single file.
+    Run {
+        input: String,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// Draw one of the thesis figures from existing results.
+    Plot { figure: Figure },
+    /// Expand the `[sweep]` section of a config file and run every combination.
+    Sweep {
+        file: String,
+        /// Only list the runs the sweep expands to.
+        #[arg(long)]
+        list: bool,
+        #[command(flatten)]
+        options: RunArgs,
+    },
+    /// Check config files without running anything.
+    Validate { input: String },
+    /// Print the fully resolved config(s) a file expands to.
+    Inspect { file: String },
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Number of seeds to run, overriding `simulation.seeds`.
+    #[arg(long)]
+    pub seeds: Option<u64>,
+
+    /// First seed to run; later seeds follow on from it.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Replace the most recent run (the one `latest` points at) instead of
+    /// adding a new timestamped run; earlier runs are kept.
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Figure {
+    #[value(name = "figure_2b")]
+    Fig2b,
+    #[value(name = "figure_3a")]
+    Fig3a,
+    #[value(name = "figure_3b")]
+    Fig3b,
+    Test,
+}
+
+impl Cli {
+    /// -1 for quiet, 0 by default and one more per `-v`.
+    pub fn verbosity(&self) -> i8 {
+        if self.quiet { -1 } else { self.verbose as i8 }
+    }
+}
@@ -0,0 +1,207 @@
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float32Builder, Float64Builder, RecordBatch, StringBuilder,
+    UInt64Builder,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Float64Type, Schema, SchemaRef, UInt64Type};
+use parquet::arrow::ArrowWriter;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::utils::OutputKind;
+
+/// Rows buffered before they are written out as one record batch.
+const BATCH_ROWS: usize = 8192;
+
+/// Parquet output file taking the same string rows as the CSV writers.
+pub struct ParquetWriter {
+    schema: SchemaRef,
+    rows: Vec<Vec<String>>,
+    writer: ArrowWriter<std::fs::File>,
+}
+
+impl ParquetWriter {
+    pub fn new(
+        file: std::fs::File,
+        kind: OutputKind,
+        header: &[String],
+    ) -> Result<ParquetWriter, Box<dyn Error>> {
+        let fields: Vec<Field> = header
+            .iter()
+            .map(|name| Field::new(name, column_type(kind, name), true))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+        Ok(ParquetWriter {
+            schema,
+            rows: Vec::new(),
+            writer,
+        })
+    }
+
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<(), Box<dyn Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let row: Vec<String> = record
+            .into_iter()
+            .map(|field| field.as_ref().to_string())
+            .collect();
+        if row.len() != self.schema.fields().len() {
+            return Err(format!(
+                "row has {} fields, expected {}",
+                row.len(),
+                self.schema.fields().len()
+            )
+            .into());
+        }
+
+        self.rows.push(row);
+        if self.rows.len() >= BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows; row groups are only cut once full.
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.write_batch()
+    }
+
+    /// Writes the buffered rows and the footer the file needs to be read.
+    pub fn close(mut self) -> Result<(), Box<dyn Error>> {
+        self.write_batch()?;
+        self.writer.close()?;
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut columns: Vec<ArrayRef> = Vec::with_capacity(self.schema.fields().len());
+        for (j, field) in self.schema.fields().iter().enumerate() {
+            let values = self.rows.iter().map(|row| row[j].as_str());
+            let parse_error = |value: &str| format!("{}: cannot parse `{}`", field.name(), value);
+
+            let column: ArrayRef = match field.data_type() {
+                DataType::UInt64 => {
+                    let mut builder = UInt64Builder::with_capacity(self.rows.len());
+                    for value in values {
+                        builder.append_option(parse_field(value).map_err(|_| parse_error(value))?);
+                    }
+                    Arc::new(builder.finish())
+                }
+                DataType::Float32 => {
+                    let mut builder = Float32Builder::with_capacity(self.rows.len());
+                    for value in values {
+                        builder.append_option(parse_field(value).map_err(|_| parse_error(value))?);
+                    }
+                    Arc::new(builder.finish())
+                }
+                DataType::Float64 => {
+                    let mut builder = Float64Builder::with_capacity(self.rows.len());
+                    for value in values {
+                        builder.append_option(parse_field(value).map_err(|_| parse_error(value))?);
+                    }
+                    Arc::new(builder.finish())
+                }
+                _ => {
+                    let mut builder = StringBuilder::new();
+                    for value in values {
+                        builder.append_value(value);
+                    }
+                    Arc::new(builder.finish())
+                }
+            };
+            columns.push(column);
+        }
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        self.rows.clear();
+        Ok(())
+    }
+}
+
+/// Values of `variable` per agent ID at the last step of a long Parquet file.
+pub fn read_final_step(path: &Path, variable: &str) -> Result<Vec<Option<f64>>, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+    let mut last_step = None;
+    let mut values: Vec<Option<f64>> = Vec::new();
+
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| format!("{}: no `{}` column", path.display(), name))
+        };
+        let wrong_type = |name: &str| format!("{}: unexpected type of `{}`", path.display(), name);
+        let steps = column("step")?
+            .as_primitive_opt::<UInt64Type>()
+            .ok_or_else(|| wrong_type("step"))?;
+        let agents = column("agent")?
+            .as_primitive_opt::<UInt64Type>()
+            .ok_or_else(|| wrong_type("agent"))?;
+        let variables = column("variable")?
+            .as_string_opt::<i32>()
+            .ok_or_else(|| wrong_type("variable"))?;
+        // Ranks and counts are stored as integers, everything else as floats.
+        let recorded = cast(column("value")?, &DataType::Float64)?;
+        let recorded = recorded.as_primitive::<Float64Type>();
+
+        for row in 0..batch.num_rows() {
+            if variables.value(row) != variable {
+                continue;
+            }
+            let step = steps.value(row);
+            if last_step != Some(step) {
+                last_step = Some(step);
+                values.clear();
+            }
+            let agent = agents.value(row) as usize;
+            if values.len() <= agent {
+                values.resize(agent + 1, None);
+            }
+            values[agent] = recorded.is_valid(row).then(|| recorded.value(row));
+        }
+    }
+
+    Ok(values)
+}
+
+/// Empty fields, left for agents not in the population, become nulls.
+fn parse_field<T: std::str::FromStr>(value: &str) -> Result<Option<T>, T::Err> {
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        value.parse().map(Some)
+    }
+}
+
+/// Arrow type of `column`, at the precision the simulation records it with.
+fn column_type(kind: OutputKind, column: &str) -> DataType {
+    match (kind, column) {
+        (_, "step" | "agent" | "visitor" | "host" | "parent" | "generation") => DataType::UInt64,
+        (_, "role" | "variable" | "action") => DataType::Utf8,
+        (OutputKind::Dyads, "hawk_hawk" | "hawk_dove" | "dove_hawk" | "dove_dove" | "refused") => {
+            DataType::UInt64
+        }
+        (OutputKind::TotalInteractions | OutputKind::OutScore, "value") => DataType::UInt64,
+        (OutputKind::EvoStats, _) => DataType::Float64,
+        _ => DataType::Float32,
+    }
+}
@@ -2,13 +2,108 @@ use plotters::prelude::*;
 use plotters::style::RGBColor;
 
 use std::fs::File;
+use std::path::Path;
+
+use crate::utils::{LONG_COLUMNS, OutputKind, latest_run};
+
+/// Values of `variable` per agent ID at the last recorded step, `None` if absent.
+fn read_final_step(
+    path: &Path,
+    variable: &str,
+) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = csv::Reader::from_reader(file);
+    let header = reader.headers()?.clone();
+    let mut values: Vec<Option<f64>> = Vec::new();
+
+    if header.iter().eq(LONG_COLUMNS) {
+        let mut last_step = None;
+        for record in reader.records() {
+            let record = record?;
+            if &record[3] != variable {
+                continue;
+            }
+            let step: u64 = record[0].parse()?;
+            if last_step != Some(step) {
+                last_step = Some(step);
+                values.clear();
+            }
+            let agent: usize = record[1].parse()?;
+            if values.len() <= agent {
+                values.resize(agent + 1, None);
+            }
+            values[agent] = Some(record[4].parse()?);
+        }
+    } else {
+        let prefix = format!("{}_", variable);
+        if !header.iter().any(|column| column.starts_with(&prefix)) {
+            return Err(format!("{}: no `{}` columns", path.display(), prefix).into());
+        }
+        let Some(last_record) = reader.records().last() else {
+            return Ok(values);
+        };
+        let last_record = last_record?;
+        for (column, field) in header.iter().zip(last_record.iter()) {
+            let Some(Ok(agent)) = column.strip_prefix(&prefix).map(str::parse::<usize>) else {
+                continue;
+            };
+            if values.len() <= agent {
+                values.resize(agent + 1, None);
+            }
+            values[agent] = if field.is_empty() {
+                None
+            } else {
+                Some(field.parse()?)
+            };
+        }
+    }
+
+    Ok(values)
+}
+
+/// `read_final_step` for seed 0 of `kind` in `run`, from CSV or Parquet.
+fn read_output(
+    run: &Path,
+    kind: OutputKind,
+    variable: &str,
+) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    let csv = run.join(format!("{}_0.csv", kind.stem()));
+    let parquet = run.join(format!("{}_0.parquet", kind.stem()));
+
+    if csv.exists() || !parquet.exists() {
+        read_final_step(&csv, variable)
+    } else {
+        read_parquet(&parquet, variable)
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn read_parquet(
+    path: &Path,
+    variable: &str,
+) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    crate::columnar::read_final_step(path, variable)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn read_parquet(
+    path: &Path,
+    _variable: &str,
+) -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "{}: this build does not include the `parquet` feature",
+        path.display()
+    )
+    .into())
+}
 
 pub fn figure_2b_plot(
     host_mat: Vec<Vec<(u32, f64)>>,
     visit_mat: Vec<Vec<(u32, f64)>>,
+    path: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Create output file
-    let root = BitMapBackend::new("figure_2b.png", (1200, 800)).into_drawing_area();
+    let root = BitMapBackend::new(path, (1200, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
     // Grid dimensions: 10 rows (strategies) × 20 cols (ranks)
@@ -87,7 +182,7 @@ pub fn figure_2b_plot(
     Ok(())
 }
 
-pub fn figure_2b() -> Result<(), Box<dyn std::error::Error>> {
+pub fn figure_2b(output: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let ff = vec![
         "0.1", "0.2", "0.3", "0.4", "0.5", "0.6", "0.7", "0.8", "0.9", "1.0",
     ];
@@ -96,63 +191,28 @@ pub fn figure_2b() -> Result<(), Box<dyn std::error::Error>> {
     let mut visit_mat: Vec<Vec<(u32, f64)>> = Vec::new();
 
     for file in ff {
-        let out_score = File::open(format!(
-            "Output/figure_2b/ff_{}/OutScore_0.csv",
-            file
-        ))?;
-        let strategy_host = File::open(format!(
-            "Output/figure_2b/ff_{}/StrategyHost_0.csv",
-            file
-        ))?;
-        let strategy_visit = File::open(format!(
-            "Output/figure_2b/ff_{}/StrategyVisit_0.csv",
-            file
-        ))?;
-
-        // Create a CSV reader
-        let mut out_score_data = csv::Reader::from_reader(out_score);
-        let mut strategy_host_data = csv::Reader::from_reader(strategy_host);
-        let mut strategy_visit_data = csv::Reader::from_reader(strategy_visit);
-
-        let mut out_score_vec: Vec<u32> = Vec::new();
-        let mut host_vec: Vec<f64> = Vec::new();
-        let mut visit_vec: Vec<f64> = Vec::new();
-
-        if let Some(last_record) = out_score_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                out_score_vec.push(field.parse()?);
-            }
-        }
-
-        // Similarly for other files
-        if let Some(last_record) = strategy_host_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                host_vec.push(field.parse()?);
-            }
-        }
-
-        if let Some(last_record) = strategy_visit_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                visit_vec.push(field.parse()?);
-            }
-        }
+        let run = latest_run(&output.join("figure_2b").join(format!("ff_{}", file)))?;
+        let out_score_vec = read_output(&run, OutputKind::OutScore, "rank")?;
+        let host_vec = read_output(&run, OutputKind::StrategyHost, "hawk")?;
+        let visit_vec = read_output(&run, OutputKind::StrategyVisit, "hawk")?;
 
         let mut host_tup: Vec<(u32, f64)> = Vec::new();
         let mut visit_tup: Vec<(u32, f64)> = Vec::new();
 
         for i in 0..out_score_vec.len() {
-            host_tup.push((out_score_vec[i], host_vec[i * 2]));
-            visit_tup.push((out_score_vec[i], visit_vec[i * 2]));
+            if let (Some(rank), Some(host), Some(visit)) =
+                (out_score_vec[i], host_vec[i], visit_vec[i])
+            {
+                host_tup.push((rank as u32, host));
+                visit_tup.push((rank as u32, visit));
+            }
         }
 
         host_mat.push(host_tup);
         visit_mat.push(visit_tup);
     }
 
-    figure_2b_plot(host_mat, visit_mat)?;
+    figure_2b_plot(host_mat, visit_mat, &output.join("figure_2b.png"))?;
 
     Ok(())
 }
@@ -160,9 +220,10 @@ pub fn figure_2b() -> Result<(), Box<dyn std::error::Error>> {
 pub fn figure_3a_plot(
     host_mat: Vec<Vec<(u32, f64)>>,
     visit_mat: Vec<Vec<(u32, f64)>>,
+    path: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Create output file
-    let root = BitMapBackend::new("figure_3a.png", (1200, 800)).into_drawing_area();
+    let root = BitMapBackend::new(path, (1200, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
     // Grid dimensions: 10 rows (strategies) × 20 cols (ranks)
@@ -241,7 +302,7 @@ pub fn figure_3a_plot(
     Ok(())
 }
 
-pub fn figure_3a() -> Result<(), Box<dyn std::error::Error>> {
+pub fn figure_3a(output: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let ff = vec![
         "0.1", "0.2", "0.3", "0.4", "0.5", "0.6", "0.7", "0.8", "0.9", "1.0",
     ];
@@ -250,69 +311,37 @@ pub fn figure_3a() -> Result<(), Box<dyn std::error::Error>> {
     let mut visit_mat: Vec<Vec<(u32, f64)>> = Vec::new();
 
     for file in ff {
-        let out_score = File::open(format!(
-            "Output/figure_3a/ff_{}/OutScore_0.csv",
-            file
-        ))?;
-        let strategy_host = File::open(format!(
-            "Output/figure_3a/ff_{}/StrategyHost_0.csv",
-            file
-        ))?;
-        let strategy_visit = File::open(format!(
-            "Output/figure_3a/ff_{}/StrategyVisit_0.csv",
-            file
-        ))?;
-
-        // Create a CSV reader
-        let mut out_score_data = csv::Reader::from_reader(out_score);
-        let mut strategy_host_data = csv::Reader::from_reader(strategy_host);
-        let mut strategy_visit_data = csv::Reader::from_reader(strategy_visit);
-
-        let mut out_score_vec: Vec<u32> = Vec::new();
-        let mut host_vec: Vec<f64> = Vec::new();
-        let mut visit_vec: Vec<f64> = Vec::new();
-
-        if let Some(last_record) = out_score_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                out_score_vec.push(field.parse()?);
-            }
-        }
-
-        // Similarly for other files
-        if let Some(last_record) = strategy_host_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                host_vec.push(field.parse()?);
-            }
-        }
-
-        if let Some(last_record) = strategy_visit_data.records().last() {
-            let last_record = last_record?;
-            for field in last_record.iter() {
-                visit_vec.push(field.parse()?);
-            }
-        }
+        let run = latest_run(&output.join("figure_3a").join(format!("ff_{}", file)))?;
+        let out_score_vec = read_output(&run, OutputKind::OutScore, "rank")?;
+        let host_vec = read_output(&run, OutputKind::StrategyHost, "hawk")?;
+        let visit_vec = read_output(&run, OutputKind::StrategyVisit, "hawk")?;
 
         let mut host_tup: Vec<(u32, f64)> = Vec::new();
         let mut visit_tup: Vec<(u32, f64)> = Vec::new();
 
         for i in 0..out_score_vec.len() {
-            host_tup.push((out_score_vec[i], host_vec[i * 2]));
-            visit_tup.push((out_score_vec[i], visit_vec[i * 2]));
+            if let (Some(rank), Some(host), Some(visit)) =
+                (out_score_vec[i], host_vec[i], visit_vec[i])
+            {
+                host_tup.push((rank as u32, host));
+                visit_tup.push((rank as u32, visit));
+            }
         }
 
         host_mat.push(host_tup);
         visit_mat.push(visit_tup);
     }
 
-    figure_3a_plot(host_mat, visit_mat)?;
+    figure_3a_plot(host_mat, visit_mat, &output.join("figure_3a.png"))?;
 
     Ok(())
 }
 
-pub fn figure_3b_plot(data: Vec<Vec<(f32, f32)>>) -> Result<(), Box<dyn std::error::Error>> {
-    let root = BitMapBackend::new("figure_3b.png", (800, 800)).into_drawing_area();
+pub fn figure_3b_plot(
+    data: Vec<Vec<(f32, f32)>>,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = BitMapBackend::new(path, (800, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
     // Determine axis ranges
@@ -370,7 +399,7 @@ pub fn figure_3b_plot(data: Vec<Vec<(f32, f32)>>) -> Result<(), Box<dyn std::err
     Ok(())
 }
 
-pub fn figure_3b() -> Result<(), Box <dyn std::error::Error>>{
+pub fn figure_3b(output: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let pop = vec!["20", "50", "100", "200", "500"];
     let ff = vec![
         "0.1", "0.2", "0.3", "0.4", "0.5", "0.6", "0.7", "0.8", "0.9", "1.0",
@@ -382,51 +411,44 @@ pub fn figure_3b() -> Result<(), Box <dyn std::error::Error>>{
         let mut data_pop: Vec<(f32, f32)> = Vec::new();
 
         for file in ff.iter() {
-            let hstrat = File::open(format!("Output/figure_3b/{}/ff_{}/StrategyHost_0.csv", num, file))?;
-            let vstrat = File::open(format!("Output/figure_3b/{}/ff_{}/StrategyVisit_0.csv", num, file))?;
-
-            let mut hstrat_data = csv::Reader::from_reader(hstrat);
-            let mut vstrat_data = csv::Reader::from_reader(vstrat);
-
-            let mut hstrat_vec: Vec<f32> = Vec::new();
-            let mut vstrat_vec: Vec<f32> = Vec::new();
-
-            if let Some(last_record) = hstrat_data.records().last() {
-                let last_record = last_record?;
-                for field in last_record.iter() {
-                    hstrat_vec.push(field.parse()?);
-                }
-            }
-
-            if let Some(last_record) = vstrat_data.records().last() {
-                let last_record = last_record?;
-                for field in last_record.iter() {
-                    vstrat_vec.push(field.parse()?);
-                }
-            }
+            let run = latest_run(
+                &output
+                    .join("figure_3b")
+                    .join(num)
+                    .join(format!("ff_{}", file)),
+            )?;
+            let hstrat_vec = read_output(&run, OutputKind::StrategyHost, "hawk")?;
+            let vstrat_vec = read_output(&run, OutputKind::StrategyVisit, "hawk")?;
 
             let mut count = 0;
-
-            for i in 0..hstrat_vec.len()/2 {
-                if hstrat_vec[i*2] >= 0.9 && vstrat_vec[i*2] >= 0.9 {
-                    count += 1;
+            let mut agents = 0;
+
+            for (host, visit) in hstrat_vec.iter().zip(&vstrat_vec) {
+                if let (Some(host), Some(visit)) = (host, visit) {
+                    agents += 1;
+                    if *host >= 0.9 && *visit >= 0.9 {
+                        count += 1;
+                    }
                 }
             }
 
-
-            data_pop.push((100.0 * (count as f32/hstrat_vec.len() as f32) as f32, file.parse()?));
+            if agents == 0 {
+                return Err(format!("{}: no agents at the last step", run.display()).into());
+            }
+            data_pop.push((100.0 * (count as f32 / agents as f32), file.parse()?));
         }
         data.push(data_pop);
     }
 
-    let _ = figure_3b_plot(data);
+    figure_3b_plot(data, &output.join("figure_3b.png"))?;
 
     Ok(())
 }
 
-pub fn test_figure() -> Result<(), Box<dyn std::error::Error>> {
+pub fn test_figure(output: &Path) -> Result<(), Box<dyn std::error::Error>> {
     // Create a drawing backend (bitmap in this case)
-    let root = BitMapBackend::new("multiple_lines.png", (800, 600)).into_drawing_area();
+    let path = output.join("multiple_lines.png");
+    let root = BitMapBackend::new(&path, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
     // Define chart properties
@@ -1,17 +1,41 @@
+mod cli;
+#[cfg(feature = "parquet")]
+mod columnar;
 mod data;
 mod utils;
 
+use chrono::Local;
+use clap::Parser;
+use cli::{Cli, Command, Figure, RunArgs};
 use data::{figure_2b, figure_3a, figure_3b, test_figure};
+use rand::distr::Uniform;
 use rand::prelude::{IndexedRandom, SliceRandom};
+use rand::rngs::ThreadRng;
+use rand::seq::index::sample;
 use rand::{Rng, rng};
+use rand_distr::{Distribution, LogNormal, Normal, Pareto, Poisson};
 use rayon::prelude::*;
-use std::env;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::ops::Range;
 use std::ops::{Index, IndexMut};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
 use utils::{
-    AgentInteractionTracker, AgentParameters, InteractionTracker, PayoffScores, RootConfig,
-    create_directories, delete_directories, get_config_files, read_config_file, run_track_vars,
+    AgentInteractionTracker, AgentParameters, ContestEffects, DeathRule, Depletion, DyadTracker,
+    EnergyParameters, HostParameters, HostRule, InteractionTracker, LATEST_LINK, Manifest,
+    OutputManager, Overflow, Overrides, Paths, PayoffScores, PopulationAction, RankingMode,
+    RankingParameters, RootConfig, Schedule, ScoreDistribution, Trackers, TurnoverParameters,
+    VisitCount, VisitParameters, create_run_directory, generate_initialscores_csv,
+    generate_population_csv, generate_turnover_csv, get_config_sources, host_name,
+    list_output_files, read_config_file, read_score_file, report_config_issues, reuse_latest_run,
+    run_track_vars, set_verbosity, update_latest, validate_config_files, verbosity, write_manifest,
 };
 
+/// Agents always left active, so every visitor has a host to pick.
+const MIN_ACTIVE_AGENTS: usize = 2;
+
 enum Role {
     Host,
     Visitor,
@@ -60,18 +84,44 @@ struct Agent {
     agent_id: AgentId,
     agent_param: AgentParameters,
     score: f64,
+    intrinsic_score: f64,
     total_payoff: f64,
     current_partner: AgentId,
     strategy: StratVector,
     current_strategy: Strategy,
     current_payoff: f64,
+    acceptance: Vec<[f64; 2]>,
+    known_hawks: Vec<bool>,
+    generation: u32,
+    joined: Option<u64>,
+    left: Option<u64>,
+    energy: f64,
+    last_total_payoff: f64,
+    ew_payoff: f64,
+    payoff_window: VecDeque<f64>,
 }
 
+#[derive(Clone)]
 struct StratVector {
     visit: [f64; 2],
     host: [f64; 2],
 }
 
+enum ScoreDraw {
+    Uniform(Uniform<f64>),
+    Normal(Normal<f64>),
+    LogNormal(LogNormal<f64>),
+    PowerLaw(Pareto<f64>),
+    Ranks,
+    File(Vec<f64>),
+}
+
+struct ScoreSampler {
+    draw: ScoreDraw,
+    max_agents: usize,
+    offset: f64,
+}
+
 struct PayoffMap {
     hh: f32,
     hd: f32,
@@ -82,29 +132,49 @@ struct PayoffMap {
 }
 
 impl Agent {
-    fn new(agent_id: AgentId, score: f64, agent_param: AgentParameters) -> Agent {
+    fn new(agent_id: AgentId, score: f64, agent_param: AgentParameters, pop: usize) -> Agent {
         Agent {
             agent_id,
             agent_param,
             score,
+            intrinsic_score: score,
             total_payoff: 0.0,
             current_partner: AgentId(0),
             strategy: StratVector::new(),
             current_strategy: Strategy::None,
             current_payoff: 0.0,
+            acceptance: vec![[1.0, 1.0]; pop],
+            known_hawks: vec![false; pop],
+            generation: 0,
+            joined: None,
+            left: None,
+            energy: 0.0,
+            last_total_payoff: 0.0,
+            ew_payoff: 0.0,
+            payoff_window: VecDeque::new(),
         }
     }
 
+    fn is_active(&self) -> bool {
+        self.joined.is_some() && self.left.is_none()
+    }
+
+    /// Payoff per step since the agent joined.
+    fn payoff_rate(&self, step: u64) -> f64 {
+        let steps = step.saturating_sub(self.joined.unwrap_or(0)).max(1);
+        self.total_payoff / steps as f64
+    }
+
     fn partner_pick(&mut self, temp_vec: &[usize], network: &mut Network) -> AgentId {
         let mut rng = rng();
         let mut friend_id: Option<usize> = None;
         let rand_tremble: f64 = rng.random();
 
         if rand_tremble < self.agent_param.net_tremble {
-            for i in 0..temp_vec.len() {
+            for &id in temp_vec {
                 network.discount_weight(
                     self.agent_id,
-                    AgentId(i as u32),
+                    AgentId(id as u32),
                     self.agent_param.net_discount,
                 );
             }
@@ -131,7 +201,7 @@ impl Agent {
 
                 network.discount_weight(
                     self.agent_id,
-                    AgentId(i as u32),
+                    AgentId(temp_vec[i] as u32),
                     self.agent_param.net_discount,
                 );
             }
@@ -142,6 +212,31 @@ impl Agent {
         }
     }
 
+    fn redraw_partner(&mut self, candidates: &[usize], network: &Network) -> AgentId {
+        let mut rng = rng();
+
+        let partial_sum: Vec<f64> = candidates
+            .iter()
+            .scan(0.0, |acc, &id| {
+                *acc += network[self.agent_id][id];
+                Some(*acc)
+            })
+            .collect();
+
+        let rand_prob: f64 = rng.random();
+        let interaction_random_draw: f64 = rand_prob * partial_sum.last().unwrap_or(&0.0);
+
+        let friend_id: Option<usize> = partial_sum
+            .iter()
+            .position(|&acc| interaction_random_draw <= acc)
+            .map(|i| candidates[i]);
+
+        let partner_id: AgentId =
+            AgentId(friend_id.unwrap_or_else(|| *candidates.choose(&mut rng).unwrap()) as u32);
+        self.current_partner = partner_id;
+        partner_id
+    }
+
     fn choose_strategy(&mut self, role: Role) {
         let mut rng = rng();
         let tremble_draw: f64 = rng.random();
@@ -212,7 +307,8 @@ impl Agent {
             }
         }
 
-        self.total_payoff += self.current_payoff
+        self.total_payoff += self.current_payoff;
+        self.energy += self.current_payoff;
     }
 
     fn normalize_strategy_weights(&mut self, role: Role) {
@@ -248,8 +344,68 @@ impl Agent {
         }
     }
 
-    fn update_score(&mut self) {
-        self.score = self.total_payoff;
+    fn accept_visitor(&self, visitor: AgentId, host_param: &HostParameters) -> bool {
+        match host_param.rule {
+            HostRule::Always => true,
+            HostRule::Learned => {
+                let [accept, refuse] = self.acceptance[visitor.0 as usize];
+                let accept_draw: f64 = rng().random();
+                accept_draw * (accept + refuse) < accept
+            }
+            HostRule::RefuseHawks => !self.known_hawks[visitor.0 as usize],
+        }
+    }
+
+    fn add_acceptance_payoff(
+        &mut self,
+        visitor: AgentId,
+        accepted: bool,
+        host_param: &HostParameters,
+    ) {
+        let index = if accepted { 0 } else { 1 };
+
+        self.acceptance[visitor.0 as usize][index] +=
+            self.current_payoff * host_param.acceptance_learning_speed;
+    }
+
+    fn observe_visitor(&mut self, visitor: AgentId, strategy: Strategy) {
+        self.known_hawks[visitor.0 as usize] = matches!(strategy, Strategy::Hawk);
+    }
+
+    fn record_step_payoff(&mut self, ranking: &RankingParameters) {
+        let step_payoff = self.total_payoff - self.last_total_payoff;
+        self.last_total_payoff = self.total_payoff;
+
+        match ranking.mode {
+            RankingMode::Exponential => {
+                self.ew_payoff =
+                    ranking.decay * self.ew_payoff + (1.0 - ranking.decay) * step_payoff;
+            }
+            RankingMode::Window => {
+                self.payoff_window.push_back(step_payoff);
+                if self.payoff_window.len() > ranking.window {
+                    self.payoff_window.pop_front();
+                }
+            }
+            RankingMode::Fixed | RankingMode::Cumulative | RankingMode::Elo => {}
+        }
+    }
+
+    fn update_score(&mut self, ranking: &RankingParameters) {
+        let payoff_score = match ranking.mode {
+            RankingMode::Fixed | RankingMode::Elo => return,
+            RankingMode::Cumulative => self.total_payoff,
+            RankingMode::Exponential => self.ew_payoff,
+            RankingMode::Window => self.payoff_window.iter().sum(),
+        };
+
+        // A payoff-based refresh re-estimates ability, so contest effects decay toward it.
+        self.score = payoff_score;
+        self.intrinsic_score = payoff_score;
+    }
+
+    fn decay_contest_effects(&mut self, contest_effects: &ContestEffects) {
+        self.score += contest_effects.decay * (self.intrinsic_score - self.score);
     }
 
 }
@@ -268,6 +424,18 @@ impl StratVector {
             Role::Visitor => &mut self.visit,
         }
     }
+
+    fn mutate(&self, mutation: f64) -> StratVector {
+        let mut rng = rng();
+        let mut mutated = self.clone();
+
+        for weight in mutated.visit.iter_mut().chain(mutated.host.iter_mut()) {
+            let noise: f64 = rng.random_range(-mutation..=mutation);
+            *weight = (*weight * (1.0 + noise)).max(f64::MIN_POSITIVE);
+        }
+
+        mutated
+    }
 }
 
 impl Network {
@@ -285,9 +453,42 @@ impl Network {
         self.0[agent_id.0 as usize][partner_id.0 as usize] *= 1.0 - net_discount;
     }
 
+    fn reset_agent(&mut self, agent_id: AgentId, active: &[usize]) {
+        let index = agent_id.0 as usize;
+        let others: Vec<usize> = active.iter().filter(|&&j| j != index).cloned().collect();
+        let initial_weight = 1.0 / others.len() as f64;
+
+        for weight in self[agent_id].iter_mut() {
+            *weight = 0.0;
+        }
+
+        for &j in &others {
+            self.0[index][j] = initial_weight;
+            self.0[j][index] = initial_weight;
+            self.normalize_network_weights(AgentId(j as u32));
+        }
+    }
+
+    fn remove_agent(&mut self, agent_id: AgentId) {
+        let index = agent_id.0 as usize;
+
+        for weight in self[agent_id].iter_mut() {
+            *weight = 0.0;
+        }
+
+        for j in 0..self.0.len() {
+            self.0[j][index] = 0.0;
+            self.normalize_network_weights(AgentId(j as u32));
+        }
+    }
+
     fn normalize_network_weights(&mut self, agent_id: AgentId) {
         let sum: f64 = self[agent_id].iter().sum();
 
+        if sum == 0.0 {
+            return;
+        }
+
         for weight in self[agent_id].iter_mut(){
             *weight /= sum;
         }
@@ -313,7 +514,8 @@ fn game(
     agents: &mut Vec<Agent>,
     payoffs: &PayoffMap,
     interaction_tracker: &mut InteractionTracker,
-    agent_interaction_tracker: &mut Vec<AgentInteractionTracker>,
+    trackers: &mut Trackers,
+    config: &RootConfig,
 ) {
     let visitor_score: f64 = agents[visitor].score;
     let host_score: f64 = agents[host].score;
@@ -324,194 +526,944 @@ fn game(
     match (visitor_strategy, host_strategy) {
         (Strategy::Hawk, Strategy::Hawk) => {
             interaction_tracker.hawk_hawk += 1;
-            agent_interaction_tracker[visitor].hawk_hawk += 1;
-            agent_interaction_tracker[host].hawk_hawk += 1;
-            if visitor_score > host_score {
-                agents[visitor].current_payoff = payoffs.win as f64;
-                agents[host].current_payoff = payoffs.lose as f64;
+            trackers.agents[visitor].hawk_hawk += 1;
+            trackers.agents[host].hawk_hawk += 1;
+            let (winner, loser) = if visitor_score > host_score {
+                (visitor, host)
             } else {
-                agents[visitor].current_payoff = payoffs.lose as f64;
-                agents[host].current_payoff = payoffs.win as f64;
+                (host, visitor)
+            };
+            agents[winner].current_payoff = payoffs.win as f64;
+            agents[loser].current_payoff = payoffs.lose as f64;
+
+            let ranking = config.ranking();
+            if ranking.mode == RankingMode::Elo {
+                update_elo(winner, loser, agents, &ranking);
+            }
+
+            if let Some(contest_effects) = &config.contest_effects {
+                agents[winner].score += contest_effects.winner_effect;
+                agents[loser].score -= contest_effects.loser_effect;
             }
         }
         (Strategy::Hawk, Strategy::Dove) => {
             agents[visitor].current_payoff = payoffs.hd as f64;
             agents[host].current_payoff = payoffs.dh as f64;
             interaction_tracker.hawk_dove += 1;
-            agent_interaction_tracker[visitor].hawk_dove += 1;
-            agent_interaction_tracker[host].hawk_dove += 1;
+            trackers.agents[visitor].hawk_dove += 1;
+            trackers.agents[host].hawk_dove += 1;
         }
         (Strategy::Dove, Strategy::Hawk) => {
             agents[visitor].current_payoff = payoffs.dh as f64;
             agents[host].current_payoff = payoffs.hd as f64;
             interaction_tracker.dove_hawk += 1;
-            agent_interaction_tracker[visitor].dove_hawk += 1;
-            agent_interaction_tracker[host].dove_hawk += 1;
+            trackers.agents[visitor].dove_hawk += 1;
+            trackers.agents[host].dove_hawk += 1;
         }
         (Strategy::Dove, Strategy::Dove) => {
             agents[visitor].current_payoff = payoffs.dd as f64;
             agents[host].current_payoff = payoffs.dd as f64;
             interaction_tracker.dove_dove += 1;
-            agent_interaction_tracker[visitor].dove_dove += 1;
-            agent_interaction_tracker[host].dove_dove += 1;
+            trackers.agents[visitor].dove_dove += 1;
+            trackers.agents[host].dove_dove += 1;
         }
         _ => unreachable!(),
     }
+
+    if let Some(dyad_tracker) = &mut trackers.dyads {
+        dyad_tracker.record(
+            visitor,
+            host,
+            (visitor_strategy, host_strategy),
+            agents[visitor].current_payoff,
+            agents[host].current_payoff,
+        );
+    }
+}
+
+fn update_elo(winner: AgentId, loser: AgentId, agents: &mut [Agent], ranking: &RankingParameters) {
+    let winner_rating = agents[winner.0 as usize].score;
+    let loser_rating = agents[loser.0 as usize].score;
+
+    let expected_win = 1.0 / (1.0 + 10f64.powf((loser_rating - winner_rating) / ranking.elo_scale));
+    let change = ranking.k_factor * (1.0 - expected_win);
+
+    // The rating change is earned ability, so contest effects decay toward it.
+    for (agent, change) in [(winner, change), (loser, -change)] {
+        agents[agent.0 as usize].score += change;
+        agents[agent.0 as usize].intrinsic_score += change;
+    }
+}
+
+impl ScoreSampler {
+    fn new(config: &RootConfig, paths: &Paths) -> Result<ScoreSampler, Box<dyn Error>> {
+        let max_agents = config.max_agents();
+
+        let draw = match config.initial_scores.clone() {
+            None => ScoreDraw::Uniform(Uniform::new(0.0, 1.0)?),
+            Some(ScoreDistribution::Uniform { low, high }) => {
+                ScoreDraw::Uniform(Uniform::new(low, high)?)
+            }
+            Some(ScoreDistribution::Normal { mean, sd }) => {
+                ScoreDraw::Normal(Normal::new(mean, sd)?)
+            }
+            Some(ScoreDistribution::LogNormal { mu, sigma }) => {
+                ScoreDraw::LogNormal(LogNormal::new(mu, sigma)?)
+            }
+            // p(x) ~ x^-alpha above x_min, i.e. a Pareto tail with shape alpha - 1.
+            Some(ScoreDistribution::PowerLaw { alpha, x_min }) => {
+                ScoreDraw::PowerLaw(Pareto::new(x_min, alpha - 1.0)?)
+            }
+            Some(ScoreDistribution::Ranks) => ScoreDraw::Ranks,
+            Some(ScoreDistribution::File { path }) => {
+                let scores = read_score_file(&path, paths)?;
+                if scores.len() < max_agents {
+                    return Err(format!(
+                        "{} holds {} scores but the run needs {}",
+                        path,
+                        scores.len(),
+                        max_agents
+                    )
+                    .into());
+                }
+                ScoreDraw::File(scores)
+            }
+        };
+
+        // Elo ratings start level; the drawn ability only breaks ties in the first contests.
+        let ranking = config.ranking();
+        let offset = match ranking.mode {
+            RankingMode::Elo => ranking.initial_rating,
+            _ => 0.0,
+        };
+
+        Ok(ScoreSampler {
+            draw,
+            max_agents,
+            offset,
+        })
+    }
+
+    fn initial_scores(&self) -> Vec<f64> {
+        let mut rng = rng();
+
+        match &self.draw {
+            ScoreDraw::Ranks => {
+                let mut scores: Vec<f64> = (1..=self.max_agents)
+                    .map(|rank| self.offset + rank as f64 / self.max_agents as f64)
+                    .collect();
+                scores.shuffle(&mut rng);
+                scores
+            }
+            _ => (0..self.max_agents)
+                .map(|id| self.sample(AgentId(id as u32)))
+                .collect(),
+        }
+    }
+
+    fn sample(&self, agent_id: AgentId) -> f64 {
+        let mut rng = rng();
+
+        let score = match &self.draw {
+            ScoreDraw::Uniform(dist) => dist.sample(&mut rng),
+            ScoreDraw::Normal(dist) => dist.sample(&mut rng),
+            ScoreDraw::LogNormal(dist) => dist.sample(&mut rng),
+            ScoreDraw::PowerLaw(dist) => dist.sample(&mut rng),
+            ScoreDraw::Ranks => {
+                rng.random_range(1..=self.max_agents) as f64 / self.max_agents as f64
+            }
+            ScoreDraw::File(scores) => scores[agent_id.0 as usize],
+        };
+
+        self.offset + score
+    }
+}
+
+struct Visit {
+    visitor: AgentId,
+    host: AgentId,
+    visitor_strategy: Strategy,
+    host_strategy: Strategy,
+    visitor_payoff: f64,
+    host_payoff: f64,
+    accepted: bool,
+}
+
+fn refuse_visit(
+    visitor: AgentId,
+    host: AgentId,
+    host_param: &HostParameters,
+    interaction_tracker: &mut InteractionTracker,
+    dyad_tracker: Option<&mut DyadTracker>,
+) -> Visit {
+    interaction_tracker.refused += 1;
+
+    if let Some(dyad_tracker) = dyad_tracker {
+        dyad_tracker.record_refusal(
+            visitor,
+            host,
+            host_param.outside_option,
+            host_param.host_outside_option,
+        );
+    }
+
+    Visit {
+        visitor,
+        host,
+        visitor_strategy: Strategy::None,
+        host_strategy: Strategy::None,
+        visitor_payoff: host_param.outside_option,
+        host_payoff: host_param.host_outside_option,
+        accepted: false,
+    }
+}
+
+fn learn_from_visit(
+    visit: &Visit,
+    agents: &mut [Agent],
+    network: &mut Network,
+    config: &RootConfig,
+) {
+    let visitor = visit.visitor.0 as usize;
+    let host = visit.host.0 as usize;
+
+    // Restore the outcome of this visit, since under a synchronous schedule the
+    // agents may have played other games since.
+    agents[visitor].current_partner = visit.host;
+    agents[visitor].current_strategy = visit.visitor_strategy;
+    agents[visitor].current_payoff = visit.visitor_payoff;
+    agents[host].current_strategy = visit.host_strategy;
+    agents[host].current_payoff = visit.host_payoff;
+
+    agents[visitor].add_network_payoff(network);
+
+    if !visit.accepted {
+        agents[visitor].total_payoff += visit.visitor_payoff;
+        agents[visitor].energy += visit.visitor_payoff;
+
+        agents[host].total_payoff += visit.host_payoff;
+        agents[host].energy += visit.host_payoff;
+        if let Some(host_param) = &config.host {
+            agents[host].add_acceptance_payoff(visit.visitor, false, host_param);
+        }
+    } else {
+        agents[visitor].add_strategy_payoff(Role::Visitor);
+        agents[host].add_strategy_payoff(Role::Host);
+
+        if let Some(energy) = &config.energy
+            && matches!(visit.visitor_strategy, Strategy::Hawk)
+            && matches!(visit.host_strategy, Strategy::Hawk)
+        {
+            agents[visitor].energy -= energy.fight_cost;
+            agents[host].energy -= energy.fight_cost;
+        }
+
+        if let Some(host_param) = &config.host {
+            agents[host].add_acceptance_payoff(visit.visitor, true, host_param);
+            agents[host].observe_visitor(visit.visitor, visit.visitor_strategy);
+        }
+    }
+
+    // agents[visitor].normalize_strategy_weights(Role::Visitor);
+    // agents[host].normalize_strategy_weights(Role::Host);
+
+    network.normalize_network_weights(visit.visitor);
+}
+
+fn visitor_sequence(
+    agent_seq: &[usize],
+    schedule: Schedule,
+    visits: &VisitParameters,
+) -> Vec<usize> {
+    let mut rng = rng();
+    let poisson = Poisson::new(visits.per_step as f64).ok();
+
+    let visit_count = |rng: &mut ThreadRng| -> usize {
+        match visits.count {
+            VisitCount::Fixed => visits.per_step as usize,
+            VisitCount::Poisson => poisson
+                .map(|poisson| poisson.sample(rng) as usize)
+                .unwrap_or(0),
+        }
+    };
+
+    match schedule {
+        Schedule::Shuffled | Schedule::Synchronous => {
+            let mut seq: Vec<usize> = Vec::new();
+            for &id in agent_seq {
+                let count = visit_count(&mut rng);
+                seq.extend(std::iter::repeat_n(id, count));
+            }
+            seq.shuffle(&mut rng);
+            seq
+        }
+        Schedule::WithReplacement => {
+            let draws: usize = agent_seq.iter().map(|_| visit_count(&mut rng)).sum();
+            (0..draws)
+                .filter_map(|_| agent_seq.choose(&mut rng).cloned())
+                .collect()
+        }
+        Schedule::Poisson => {
+            let mean = (agent_seq.len() as u32 * visits.per_step) as f64;
+            let events = Poisson::new(mean)
+                .map(|poisson| poisson.sample(&mut rng) as usize)
+                .unwrap_or(0);
+            (0..events)
+                .filter_map(|_| agent_seq.choose(&mut rng).cloned())
+                .collect()
+        }
+    }
+}
+
+fn run_turnover(
+    i: u64,
+    agents: &mut [Agent],
+    network: &mut Network,
+    trackers: &mut Trackers,
+    turnover: &TurnoverParameters,
+    sampler: &ScoreSampler,
+    config: &RootConfig,
+) -> Vec<(AgentId, AgentId)> {
+    let mut rng = rng();
+    let pop = agents.len();
+    let active: Vec<usize> = (0..pop).filter(|&j| agents[j].is_active()).collect();
+    let deaths = (turnover.deaths as usize).min(active.len().saturating_sub(1));
+
+    let dead: Vec<usize> = match turnover.death_rule {
+        DeathRule::Random => sample(&mut rng, active.len(), deaths)
+            .into_iter()
+            .map(|j| active[j])
+            .collect(),
+        DeathRule::LowPayoff => {
+            // Shuffle first so that ties in payoff are broken at random.
+            let mut ranked: Vec<usize> = active.clone();
+            ranked.shuffle(&mut rng);
+            ranked.sort_by(|&a, &b| {
+                agents[a]
+                    .payoff_rate(i)
+                    .partial_cmp(&agents[b].payoff_rate(i))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            ranked.truncate(deaths);
+            ranked
+        }
+    };
+
+    let survivors: Vec<usize> = active
+        .iter()
+        .filter(|j| !dead.contains(j))
+        .cloned()
+        .collect();
+    let mut births: Vec<(AgentId, AgentId)> = Vec::new();
+
+    for &id in &dead {
+        let parent = *survivors
+            .choose_weighted(&mut rng, |&j| agents[j].payoff_rate(i).max(0.0))
+            .unwrap_or_else(|_| survivors.choose(&mut rng).unwrap());
+
+        let mut newcomer = Agent::new(
+            AgentId(id as u32),
+            sampler.sample(AgentId(id as u32)),
+            agents[parent].agent_param,
+            pop,
+        );
+        newcomer.energy = config.energy.map_or(0.0, |energy| energy.initial);
+        newcomer.strategy = agents[parent].strategy.mutate(turnover.mutation);
+        newcomer.generation = agents[parent].generation + 1;
+        newcomer.joined = Some(i);
+        agents[id] = newcomer;
+
+        for agent in agents.iter_mut() {
+            agent.acceptance[id] = [1.0, 1.0];
+            agent.known_hawks[id] = false;
+        }
+
+        network.reset_agent(AgentId(id as u32), &active);
+        trackers.agents[id] = AgentInteractionTracker::new();
+        births.push((AgentId(id as u32), AgentId(parent as u32)));
+    }
+
+    if let Some(dyad_tracker) = &mut trackers.dyads {
+        for &(agent_id, _) in &births {
+            dyad_tracker.reset_agent(agent_id);
+        }
+    }
+
+    births
+}
+
+fn apply_population_events(
+    i: u64,
+    agents: &mut [Agent],
+    network: &mut Network,
+    config: &RootConfig,
+) -> Vec<(AgentId, PopulationAction)> {
+    let mut rng = rng();
+    let mut changes: Vec<(AgentId, PopulationAction)> = Vec::new();
+
+    for event in config
+        .population_events
+        .iter()
+        .filter(|event| event.step == i)
+    {
+        let mut active: Vec<usize> = (0..agents.len())
+            .filter(|&j| agents[j].is_active())
+            .collect();
+        let removable = (event.count as usize).min(active.len().saturating_sub(MIN_ACTIVE_AGENTS));
+
+        let affected: Vec<usize> = match event.action {
+            PopulationAction::Immigrate => (0..agents.len())
+                .filter(|&j| agents[j].joined.is_none())
+                .take(event.count as usize)
+                .collect(),
+            PopulationAction::RemoveTop => {
+                active.shuffle(&mut rng);
+                active.sort_by(|&a, &b| {
+                    agents[b]
+                        .score
+                        .partial_cmp(&agents[a].score)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                active.truncate(removable);
+                active
+            }
+            PopulationAction::RemoveRandom => sample(&mut rng, active.len(), removable)
+                .into_iter()
+                .map(|j| active[j])
+                .collect(),
+            PopulationAction::Starved => unreachable!(),
+        };
+
+        for &id in &affected {
+            match event.action {
+                PopulationAction::Immigrate => {
+                    agents[id].joined = Some(i);
+                    agents[id].energy = config.energy.map_or(0.0, |energy| energy.initial);
+                    let active: Vec<usize> = (0..agents.len())
+                        .filter(|&j| agents[j].is_active())
+                        .collect();
+                    network.reset_agent(AgentId(id as u32), &active);
+                }
+                PopulationAction::RemoveTop
+                | PopulationAction::RemoveRandom
+                | PopulationAction::Starved => {
+                    agents[id].left = Some(i);
+                    network.remove_agent(AgentId(id as u32));
+                }
+            }
+
+            changes.push((AgentId(id as u32), event.action));
+        }
+    }
+
+    changes
+}
+
+fn run_depletion(
+    i: u64,
+    agents: &mut [Agent],
+    network: &mut Network,
+    energy: &EnergyParameters,
+) -> Vec<(AgentId, PopulationAction)> {
+    let mut changes: Vec<(AgentId, PopulationAction)> = Vec::new();
+
+    if energy.depletion != Depletion::Die {
+        return changes;
+    }
+
+    let mut active = (0..agents.len()).filter(|&j| agents[j].is_active()).count();
+
+    for (id, agent) in agents.iter_mut().enumerate() {
+        if active <= MIN_ACTIVE_AGENTS {
+            break;
+        }
+
+        if agent.is_active() && agent.energy <= 0.0 {
+            agent.left = Some(i);
+            network.remove_agent(AgentId(id as u32));
+            changes.push((AgentId(id as u32), PopulationAction::Starved));
+            active -= 1;
+        }
+    }
+
+    changes
 }
 
 fn run_time_step(
     i: u64,
     agents: &mut Vec<Agent>,
-    pop: usize,
     network: &mut Network,
     payoffs: &PayoffMap,
-    dynamic_rank: bool,
-    seed: u64,
-    output_directory: &str,
-    agent_interaction_tracker: &mut Vec<AgentInteractionTracker>,
+    output: &mut OutputManager,
+    trackers: &mut Trackers,
+    sampler: &ScoreSampler,
     config: &RootConfig,
-) {
-    let mut agent_seq: Vec<usize> = (0..pop).collect();
-    let mut rng = rng();
-    agent_seq.shuffle(&mut rng);
+) -> Result<(), Box<dyn Error>> {
+    let ranking = config.ranking();
+    let mut changes = apply_population_events(i, agents, network, config);
+
+    let active: Vec<usize> = (0..agents.len())
+        .filter(|&j| agents[j].is_active())
+        .collect();
+
+    // Agents that have run out of energy sit out as visitors but can still be visited.
+    let agent_seq: Vec<usize> = match &config.energy {
+        Some(_) => active
+            .iter()
+            .filter(|&&j| agents[j].energy > 0.0)
+            .cloned()
+            .collect(),
+        None => active.clone(),
+    };
+    let schedule = config.simulation.schedule;
+    let visits = config.visits();
+    let visit_seq = visitor_sequence(&agent_seq, schedule, &visits);
 
     let mut interaction_tracker: InteractionTracker = InteractionTracker::new();
+    let mut host_visits: Vec<u32> = vec![0; agents.len()];
+    let mut pending: Vec<Visit> = Vec::new();
+
+    let mut settle = |visit: Visit, agents: &mut Vec<Agent>, network: &mut Network| {
+        if schedule == Schedule::Synchronous {
+            pending.push(visit);
+        } else {
+            learn_from_visit(&visit, agents, network, config);
+        }
+    };
+
+    for &id in &visit_seq {
+        interaction_tracker.visitors += 1;
 
-    for &id in &agent_seq {
-        let temp_vec: Vec<usize> = agent_seq.iter().filter(|&&x| x != id).cloned().collect();
+        let temp_vec: Vec<usize> = active.iter().filter(|&&x| x != id).cloned().collect();
+
+        if let Some(energy) = &config.energy {
+            agents[id].energy -= energy.visit_cost;
+        }
 
-        let host_id: AgentId = agents[id].partner_pick(&temp_vec, network);
+        let mut host_id: AgentId = agents[id].partner_pick(&temp_vec, network);
 
+        if let Some(capacity) = &config.capacity
+            && host_visits[host_id.0 as usize] >= capacity.host_capacity
         {
-            let visitor = &mut agents[id];
-            visitor.choose_strategy(Role::Visitor);
-            visitor.current_partner = host_id;
+            interaction_tracker.rejected += 1;
+
+            let open_hosts: Vec<usize> = temp_vec
+                .iter()
+                .filter(|&&x| host_visits[x] < capacity.host_capacity)
+                .cloned()
+                .collect();
+
+            if capacity.overflow == Overflow::Forfeit || open_hosts.is_empty() {
+                network.normalize_network_weights(AgentId(id as u32));
+                continue;
+            }
+
+            host_id = agents[id].redraw_partner(&open_hosts, network);
         }
 
+        if let Some(host_param) = &config.host
+            && !agents[host_id].accept_visitor(AgentId(id as u32), host_param)
         {
-            let host = &mut agents[host_id.0 as usize];
-            host.choose_strategy(Role::Host);
+            let visit = refuse_visit(
+                AgentId(id as u32),
+                host_id,
+                host_param,
+                &mut interaction_tracker,
+                trackers.dyads.as_mut(),
+            );
+            settle(visit, agents, network);
+            continue;
         }
 
-        game(
-            AgentId(id as u32),
-            host_id,
-            agents,
-            payoffs,
-            &mut interaction_tracker,
-            agent_interaction_tracker,
-        );
+        host_visits[host_id.0 as usize] += 1;
+
+        for _ in 0..visits.rounds {
+            {
+                let visitor = &mut agents[id];
+                visitor.choose_strategy(Role::Visitor);
+                visitor.current_partner = host_id;
+            }
+
+            {
+                let host = &mut agents[host_id.0 as usize];
+                host.choose_strategy(Role::Host);
+            }
 
-        agents[id].add_network_payoff(network);
+            game(
+                AgentId(id as u32),
+                host_id,
+                agents,
+                payoffs,
+                &mut interaction_tracker,
+                trackers,
+                config,
+            );
+            interaction_tracker.games += 1;
+
+            let visit = Visit {
+                visitor: AgentId(id as u32),
+                host: host_id,
+                visitor_strategy: agents[id].current_strategy,
+                host_strategy: agents[host_id].current_strategy,
+                visitor_payoff: agents[id].current_payoff,
+                host_payoff: agents[host_id].current_payoff,
+                accepted: true,
+            };
+            settle(visit, agents, network);
+        }
+    }
 
-        agents[id].add_strategy_payoff(Role::Visitor);
-        agents[host_id].add_strategy_payoff(Role::Host);
+    // Under a synchronous schedule every game in the step is played before any
+    // payoffs are learned from.
+    for visit in &pending {
+        learn_from_visit(visit, agents, network, config);
+    }
 
-        // agents[id].normalize_strategy_weights(Role::Visitor);
-        // agents[host_id].normalize_strategy_weights(Role::Host);
+    for agent in agents.iter_mut() {
+        agent.record_step_payoff(&ranking);
+    }
 
-        network.normalize_network_weights(AgentId(id as u32));
+    if let Some(contest_effects) = &config.contest_effects {
+        for agent in agents.iter_mut() {
+            agent.decay_contest_effects(contest_effects);
+        }
     }
 
-    if dynamic_rank {
-        if i % 1000 == 0 {
-            for j in 0..pop {
-                agents[j].update_score();
-            }
+    if ranking.mode != RankingMode::Fixed && i.is_multiple_of(ranking.interval) {
+        for agent in agents.iter_mut() {
+            agent.update_score(&ranking);
+        }
+    }
+
+    if let Some(energy) = &config.energy {
+        changes.extend(run_depletion(i, agents, network, energy));
+    }
+
+    if config.csv.population && !changes.is_empty() {
+        generate_population_csv(i, &changes, output)?;
+    }
+
+    if let Some(turnover) = &config.turnover
+        && i.is_multiple_of(turnover.interval)
+    {
+        let births = run_turnover(i, agents, network, trackers, turnover, sampler, config);
+
+        if config.csv.turnover {
+            generate_turnover_csv(i, &births, agents, output)?;
         }
     }
 
     run_track_vars(
         i,
-        pop,
         agents,
         network,
-        output_directory,
-        seed,
+        output,
         &interaction_tracker,
-        agent_interaction_tracker,
+        trackers,
         config,
-    );
+    )?;
+    output.end_step()
 }
 
-fn run_config_file(config: &RootConfig, out_path: &str) {
-    let seeds = config.simulation.seeds;
-    let mut rng = rng();
+fn run_config_file(
+    config: &RootConfig,
+    work_direc: &str,
+    seeds: Range<u64>,
+    paths: &Paths,
+) -> Result<(), Box<dyn Error>> {
     let max_time_step: u64 = config.simulation.max_time_step;
     let pop: u32 = config.simulation.population;
-    let dynamic_rank: bool = config.simulation.dynamic_rank;
-    let output_directory: String = config.simulation.output_directory.clone();
+    let max_agents: usize = config.max_agents();
 
     let payoffs = PayoffMap::new(config.payoffs);
 
-    let work_direc = format!("{}/{}", out_path, output_directory);
-
-    let _ = delete_directories(&work_direc);
-    create_directories(&work_direc);
+    let sampler =
+        ScoreSampler::new(config, paths).map_err(|e| format!("loading initial scores: {}", e))?;
 
-    for seed in 0..seeds {
+    for seed in seeds {
         let mut agents: Vec<Agent> = Vec::new();
-        let mut network = Network::new(pop as usize);
-        let mut agent_interaction_tracker: Vec<AgentInteractionTracker> =
-            vec![AgentInteractionTracker::new(); pop as usize];
-
-        for i in 0..pop {
-            agents.push(Agent::new(
+        let mut network = Network::new(max_agents);
+        let mut trackers = Trackers::new(max_agents, config.csv.dyads);
+        let initial_scores = sampler.initial_scores();
+
+        // Agent IDs beyond the initial population are reserved for immigrants
+        // and stay outside the network until their arrival step.
+        for (i, &score) in initial_scores.iter().enumerate() {
+            let mut agent = Agent::new(
                 AgentId(i as u32),
-                rng.random(),
+                score,
                 config.agent_parameters,
-            ));
+                max_agents,
+            );
+
+            if let Some(energy) = &config.energy {
+                agent.energy = energy.initial;
+            }
+
+            if i < pop as usize {
+                agent.joined = Some(0);
+            } else {
+                network.remove_agent(AgentId(i as u32));
+            }
+
+            agents.push(agent);
         }
 
+        let mut output = OutputManager::new(work_direc, seed, max_agents, &config.csv);
+        generate_initialscores_csv(&agents, &mut output)?;
+
         run_track_vars(
             0 as u64,
-            pop as usize,
             &agents,
             &network,
-            &work_direc,
-            seed,
+            &mut output,
             &InteractionTracker::default(pop as usize),
-            &agent_interaction_tracker,
+            &trackers,
             config,
-        );
+        )?;
 
         for i in 1..=max_time_step {
             run_time_step(
                 i,
                 &mut agents,
-                pop as usize,
                 &mut network,
                 &payoffs,
-                dynamic_rank,
-                seed,
-                &work_direc,
-                &mut agent_interaction_tracker,
+                &mut output,
+                &mut trackers,
+                &sampler,
                 config,
-            );
+            )?;
         }
+
+        output.finish()?;
     }
+
+    Ok(())
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// One config, or one sweep combination, and its output directory.
+struct Run {
+    config: RootConfig,
+    source_file: String,
+    output_directory: String,
+}
+
+/// Runs named by `input`, and the number of invalid config files.
+fn load_runs(input: &str, paths: &Paths, overrides: &Overrides) -> (Vec<Run>, usize) {
+    let sources = get_config_sources(input, paths);
+    if sources.is_empty() {
+        eprintln!(
+            "Error: no config files found at {}",
+            paths.input_file(input).display()
+        );
+        return (Vec::new(), 1);
+    }
+
+    let mut runs = Vec::new();
+    let mut invalid = 0;
+
+    for (source_file, group) in sources {
+        match read_config_file(&source_file, paths, overrides) {
+            Ok(configs) => runs.extend(configs.into_iter().map(|config| Run {
+                output_directory: paths.run_directory(&group, &config.simulation.output_directory),
+                source_file: source_file.clone(),
+                config,
+            })),
+            Err(issues) => {
+                report_config_issues(&source_file, &issues, paths);
+                invalid += 1;
+            }
+        }
+    }
+
+    (runs, invalid)
+}
+
+/// Runs whose output directories are the same or nested in one another.
+fn find_collisions(runs: &[Run], paths: &Paths) -> Vec<String> {
+    let mut collisions = Vec::new();
+
+    for (j, a) in runs.iter().enumerate() {
+        for b in &runs[j + 1..] {
+            let (dir_a, dir_b) = (
+                Path::new(&a.output_directory),
+                Path::new(&b.output_directory),
+            );
+            if dir_a.starts_with(dir_b) || dir_b.starts_with(dir_a) {
+                collisions.push(format!(
+                    "{} ({}) and {} ({}) write to overlapping directories",
+                    paths.input_file(&a.source_file).display(),
+                    a.output_directory,
+                    paths.input_file(&b.source_file).display(),
+                    b.output_directory
+                ));
+            }
+        }
+    }
+
+    collisions
+}
 
-    if args.len() < 1 {
-        panic!("Wrong arguments entered!")
+/// Runs every config in parallel, returning the number of runs that failed.
+fn run_all(runs: &[Run], options: &RunArgs, paths: &Paths, overrides: &Overrides) -> usize {
+    let collisions = find_collisions(runs, paths);
+    if !collisions.is_empty() {
+        for collision in &collisions {
+            eprintln!("Error: {}", collision);
+        }
+        eprintln!("Refusing to start; give the configs distinct output directories.");
+        process::exit(1);
     }
 
-    let path = get_config_files(&args[1]);
+    let run_id = Local::now().format("%Y%m%d-%H%M%S").to_string();
+
+    runs.par_iter()
+        .map(|run| {
+            let config = &run.config;
+            if verbosity() >= 0 {
+                println!("{}", config.description);
+            }
+
+            let reused = if options.overwrite {
+                reuse_latest_run(&run.output_directory).transpose()
+            } else {
+                None
+            };
+            let work_direc = match reused
+                .unwrap_or_else(|| create_run_directory(&run.output_directory, &run_id))
+            {
+                Ok(work_direc) => work_direc,
+                Err(e) => {
+                    eprintln!(
+                        "Error making run directory in {}: {}",
+                        run.output_directory, e
+                    );
+                    return false;
+                }
+            };
+
+            let seeds =
+                options.seed..options.seed + options.seeds.unwrap_or(config.simulation.seeds);
+            let started = Local::now();
+            let timer = Instant::now();
+            if let Err(e) = run_config_file(config, &work_direc, seeds.clone(), paths) {
+                eprintln!("Error running {}: {}", config.description, e);
+                return false;
+            }
 
-    path.par_iter().for_each(|file| {
-        print!("{}", file);
-        let config: RootConfig = read_config_file(&format!("/{}/{}", &args[1], &file));
-        println!("{}", config.description);
-        run_config_file(&config, &args[1]);
-    });
+            let manifest =
+                list_output_files(&work_direc, &config.csv).map(|files| Manifest {
+                    description: &config.description,
+                    source_file: run.source_file.clone(),
+                    config,
+                    overrides,
+                    seeds: seeds.collect(),
+                    agents: config.max_agents(),
+                    version: env!("CARGO_PKG_VERSION"),
+                    git_commit: env!("THESIS_GIT_COMMIT"),
+                    host: host_name(),
+                    started: started.to_rfc3339(),
+                    finished: Local::now().to_rfc3339(),
+                    duration_seconds: timer.elapsed().as_secs_f64(),
+                    files,
+                });
+            let manifest_written = manifest
+                .map_err(Into::into)
+                .and_then(|manifest| write_manifest(&work_direc, &manifest));
+            if let Err(e) = &manifest_written {
+                eprintln!("Error writing manifest for {}: {}", config.description, e);
+            }
+
+            if let Err(e) = update_latest(&run.output_directory, &work_direc) {
+                eprintln!(
+                    "Error updating {}/{}: {}",
+                    run.output_directory, LATEST_LINK, e
+                );
+            }
+
+            if verbosity() >= 1 {
+                println!("Finished {} -> {}", config.description, work_direc);
+            }
+            manifest_written.is_ok()
+        })
+        .filter(|succeeded| !succeeded)
+        .count()
+}
 
-    let err = figure_2b();
-    println!("{:?}", err);
-    // let _ = figure_3a();
+/// Exits with status 1 if any config was invalid or any run failed.
+fn exit_on_failure(invalid: usize, failed: usize) {
+    if failed > 0 {
+        eprintln!("{} run(s) failed", failed);
+    }
+    if invalid > 0 || failed > 0 {
+        process::exit(1);
+    }
+}
 
-    // println!("{:?}", figure_3b());
+fn main() {
+    let cli = Cli::parse();
+    set_verbosity(cli.verbosity());
+
+    if let Some(threads) = cli.threads
+        && let Err(e) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+    {
+        eprintln!("Error setting up {} threads: {}", threads, e);
+    }
 
+    let paths = Paths {
+        input: cli.input_root.clone(),
+        output: cli.output_root.clone(),
+    };
+    let overrides = Overrides::new(cli.set.clone());
+
+    match &cli.command {
+        Command::Run { input, options } => {
+            let (runs, invalid) = load_runs(input, &paths, &overrides);
+            let failed = run_all(&runs, options, &paths, &overrides);
+            exit_on_failure(invalid, failed);
+        }
+        Command::Plot { figure } => {
+            let result = match figure {
+                Figure::Fig2b => figure_2b(&paths.output),
+                Figure::Fig3a => figure_3a(&paths.output),
+                Figure::Fig3b => figure_3b(&paths.output),
+                Figure::Test => test_figure(&paths.output),
+            };
+            if let Err(e) = result {
+                eprintln!("Error plotting {:?}: {}", figure, e);
+                process::exit(1);
+            }
+        }
+        Command::Sweep {
+            file,
+            list,
+            options,
+        } => {
+            let (runs, invalid) = load_runs(file, &paths, &overrides);
+            if *list {
+                for run in &runs {
+                    println!("{} -> {}", run.config.description, run.output_directory);
+                }
+                exit_on_failure(invalid, 0);
+            } else {
+                let failed = run_all(&runs, options, &paths, &overrides);
+                exit_on_failure(invalid, failed);
+            }
+        }
+        Command::Validate { input } => {
+            if validate_config_files(input, &paths, &overrides) > 0 {
+                process::exit(1);
+            }
+        }
+        Command::Inspect { file } => {
+            let (runs, invalid) = load_runs(file, &paths, &overrides);
+            for run in runs {
+                println!("# {} -> {}", run.config.description, run.output_directory);
+                println!("{:#?}", run.config);
+            }
+            exit_on_failure(invalid, 0);
+        }
+    }
 }
@@ -1,17 +1,27 @@
-use core::panic;
 use std::io;
 
-use config::{Config, ConfigError, File};
-use csv::WriterBuilder;
-use serde::Deserialize;
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, ConfigError, Environment, File, Map, Value, ValueKind};
+use csv::{ReaderBuilder, Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::OpenOptions;
-use std::fs::{create_dir_all, remove_dir_all};
+use std::fs::{create_dir, create_dir_all, read_dir, read_link, remove_dir_all, rename};
 use std::ops::{Index, IndexMut};
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+#[cfg(windows)]
+use std::os::windows::fs::symlink_dir as symlink;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI8, Ordering};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
-use crate::{Agent, AgentId, Network};
+#[cfg(feature = "parquet")]
+use crate::columnar::ParquetWriter;
+use crate::{Agent, AgentId, Network, Strategy};
 
 impl Index<AgentId> for Vec<AgentInteractionTracker> {
     type Output = AgentInteractionTracker;
@@ -27,16 +37,43 @@ impl IndexMut<AgentId> for Vec<AgentInteractionTracker> {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct SimulationParameters {
     pub seeds: u64,
     pub max_time_step: u64,
     pub population: u32,
     pub dynamic_rank: bool,
+    /// Defaults to the name of the config file.
     pub output_directory: String,
+    pub schedule: Schedule,
 }
 
-#[derive(Debug, Deserialize, Copy, Clone)]
+impl Default for SimulationParameters {
+    fn default() -> Self {
+        SimulationParameters {
+            seeds: 1,
+            max_time_step: 100000,
+            population: 20,
+            dynamic_rank: false,
+            output_directory: String::new(),
+            schedule: Schedule::Shuffled,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    #[default]
+    Shuffled,
+    WithReplacement,
+    Poisson,
+    Synchronous,
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+#[serde(default)]
 pub struct AgentParameters {
     pub strat_learning_speed: f64,
     pub net_learning_speed: f64,
@@ -46,7 +83,21 @@ pub struct AgentParameters {
     pub net_tremble: f64,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+impl Default for AgentParameters {
+    fn default() -> Self {
+        AgentParameters {
+            strat_learning_speed: 0.01,
+            net_learning_speed: 0.01,
+            strat_discount: 0.01,
+            net_discount: 0.01,
+            strat_tremble: 0.01,
+            net_tremble: 0.01,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(default)]
 pub struct PayoffScores {
     pub hd: f32,
     pub dh: f32,
@@ -54,8 +105,235 @@ pub struct PayoffScores {
     pub hh_f: f32,
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for PayoffScores {
+    fn default() -> Self {
+        PayoffScores {
+            hd: 1.0,
+            dh: 0.4,
+            dd: 0.6,
+            hh_f: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VisitCount {
+    #[default]
+    Fixed,
+    Poisson,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct VisitParameters {
+    #[serde(default = "default_per_step")]
+    pub per_step: u32,
+    #[serde(default)]
+    pub count: VisitCount,
+    #[serde(default = "default_rounds")]
+    pub rounds: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HostRule {
+    Always,
+    Learned,
+    RefuseHawks,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct HostParameters {
+    pub rule: HostRule,
+    pub outside_option: f64,
+    #[serde(default)]
+    pub host_outside_option: f64,
+    #[serde(default)]
+    pub acceptance_learning_speed: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    Resample,
+    Forfeit,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct CapacityParameters {
+    pub host_capacity: u32,
+    pub overflow: Overflow,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathRule {
+    Random,
+    LowPayoff,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct TurnoverParameters {
+    pub interval: u64,
+    pub deaths: u32,
+    pub death_rule: DeathRule,
+    pub mutation: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PopulationAction {
+    Immigrate,
+    RemoveTop,
+    RemoveRandom,
+    #[serde(skip)]
+    Starved,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct PopulationEvent {
+    /// Step the event happens at, from 1.
+    pub step: u64,
+    pub action: PopulationAction,
+    pub count: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Depletion {
+    Inactive,
+    Die,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct EnergyParameters {
+    pub initial: f64,
+    pub visit_cost: f64,
+    pub fight_cost: f64,
+    pub depletion: Depletion,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingMode {
+    Fixed,
+    Cumulative,
+    Exponential,
+    Window,
+    Elo,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct RankingParameters {
+    pub mode: RankingMode,
+    #[serde(default = "default_rank_interval")]
+    pub interval: u64,
+    #[serde(default = "default_rank_decay")]
+    pub decay: f64,
+    #[serde(default)]
+    pub window: usize,
+    #[serde(default = "default_k_factor")]
+    pub k_factor: f64,
+    #[serde(default = "default_elo_scale")]
+    pub elo_scale: f64,
+    #[serde(default = "default_initial_rating")]
+    pub initial_rating: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct ContestEffects {
+    pub winner_effect: f64,
+    pub loser_effect: f64,
+    pub decay: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "distribution", rename_all = "snake_case")]
+pub enum ScoreDistribution {
+    Uniform { low: f64, high: f64 },
+    Normal { mean: f64, sd: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    PowerLaw { alpha: f64, x_min: f64 },
+    Ranks,
+    File { path: String },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepMode {
+    #[default]
+    Cartesian,
+    Zip,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum SweepValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl std::fmt::Display for SweepValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SweepValue::Int(value) => write!(f, "{}", value),
+            SweepValue::Float(value) => write!(f, "{}", value),
+            SweepValue::Bool(value) => write!(f, "{}", value),
+            SweepValue::Str(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<SweepValue> for Value {
+    fn from(value: SweepValue) -> Value {
+        match value {
+            SweepValue::Int(value) => Value::from(value),
+            SweepValue::Float(value) => Value::from(value),
+            SweepValue::Bool(value) => Value::from(value),
+            SweepValue::Str(value) => Value::from(value),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepValues {
+    Values(Vec<SweepValue>),
+    Range { start: f64, stop: f64, step: f64 },
+    LogRange { start: f64, stop: f64, num: u32 },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SweepAxis {
+    pub key: String,
+    #[serde(flatten)]
+    pub values: SweepValues,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SweepParameters {
+    #[serde(default)]
+    pub mode: SweepMode,
+    pub parameters: Vec<SweepAxis>,
+}
+
+/// `wide` has a column per agent and variable, `long` a row per observation.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CSVFormat {
+    #[default]
+    Wide,
+    Long,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct CSVFiles {
+    pub format: CSVFormat,
+    /// Outputs written as Parquet instead of CSV, always in the long format.
+    pub parquet: Vec<OutputKind>,
     pub weights: bool,
     pub scores: bool,
     pub totalinteractions: bool,
@@ -65,22 +343,180 @@ pub struct CSVFiles {
     pub netstd: bool,
     pub outscore: bool,
     pub totalpayoff: bool,
+    pub dyads: bool,
+    pub turnover: bool,
+    pub population: bool,
+    pub energy: bool,
 }
 
-#[derive(Debug, Deserialize)]
+/// By default only the files read by the figures in `data.rs` are written.
+impl Default for CSVFiles {
+    fn default() -> Self {
+        CSVFiles {
+            format: CSVFormat::Wide,
+            parquet: Vec::new(),
+            weights: false,
+            scores: false,
+            totalinteractions: false,
+            evostats: false,
+            strategyvisit: true,
+            strategyhost: true,
+            netstd: false,
+            outscore: true,
+            totalpayoff: false,
+            dyads: false,
+            turnover: false,
+            population: false,
+            energy: false,
+        }
+    }
+}
+
+impl CSVFiles {
+    /// Layout of `kind`; Parquet files are always long.
+    pub fn format(&self, kind: OutputKind) -> CSVFormat {
+        if self.parquet.contains(&kind) {
+            CSVFormat::Long
+        } else {
+            self.format
+        }
+    }
+}
+
+/// Steps the figure outputs were written at before schedules were configurable.
+const DEFAULT_RECORD_STEPS: [u64; 45] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 200, 400, 500, 600, 700,
+    800, 900, 1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 10000, 20000, 30000, 40000,
+    50000, 60000, 70000, 80000, 90000, 100000,
+];
+
+/// When an output is written.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSchedule {
+    /// Every N steps, starting with the initial state at step 0.
+    Every(u64),
+    /// N log-spaced steps per decade, starting at step 1.
+    Log(u32),
+    Steps(Vec<u64>),
+    Final,
+}
+
+impl RecordSchedule {
+    pub fn records(&self, step: u64, max_time_step: u64) -> bool {
+        match self {
+            RecordSchedule::Every(n) => step.is_multiple_of(*n),
+            RecordSchedule::Log(per_decade) => {
+                if step == 0 {
+                    return false;
+                }
+                // Candidates are 10^(k + j / per_decade) rounded, for the
+                // decade k the step falls in and the start of the next one.
+                let decade = (step as f64).log10().floor() as i32;
+                (0..=*per_decade).any(|j| {
+                    let exponent = decade as f64 + j as f64 / *per_decade as f64;
+                    10f64.powf(exponent).round() as u64 == step
+                })
+            }
+            RecordSchedule::Steps(steps) => steps.contains(&step),
+            RecordSchedule::Final => step == max_time_step,
+        }
+    }
+
+    fn default_steps() -> Self {
+        RecordSchedule::Steps(DEFAULT_RECORD_STEPS.to_vec())
+    }
+}
+
+/// Recording schedule of each output enabled in `[csv]`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RecordingParameters {
+    pub weights: RecordSchedule,
+    pub scores: RecordSchedule,
+    pub totalinteractions: RecordSchedule,
+    pub dyads: RecordSchedule,
+    pub evostats: RecordSchedule,
+    pub strategyvisit: RecordSchedule,
+    pub strategyhost: RecordSchedule,
+    pub netstd: RecordSchedule,
+    pub outscore: RecordSchedule,
+    pub totalpayoff: RecordSchedule,
+    pub energy: RecordSchedule,
+}
+
+impl Default for RecordingParameters {
+    fn default() -> Self {
+        RecordingParameters {
+            weights: RecordSchedule::default_steps(),
+            scores: RecordSchedule::default_steps(),
+            totalinteractions: RecordSchedule::default_steps(),
+            dyads: RecordSchedule::default_steps(),
+            evostats: RecordSchedule::Every(1),
+            strategyvisit: RecordSchedule::Every(1),
+            strategyhost: RecordSchedule::Every(1),
+            netstd: RecordSchedule::Every(1),
+            outscore: RecordSchedule::Every(1),
+            totalpayoff: RecordSchedule::Every(1),
+            energy: RecordSchedule::Every(1),
+        }
+    }
+}
+
+impl RecordingParameters {
+    fn schedules(&self) -> [(&'static str, &RecordSchedule); 11] {
+        [
+            ("weights", &self.weights),
+            ("scores", &self.scores),
+            ("totalinteractions", &self.totalinteractions),
+            ("dyads", &self.dyads),
+            ("evostats", &self.evostats),
+            ("strategyvisit", &self.strategyvisit),
+            ("strategyhost", &self.strategyhost),
+            ("netstd", &self.netstd),
+            ("outscore", &self.outscore),
+            ("totalpayoff", &self.totalpayoff),
+            ("energy", &self.energy),
+        ]
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct RootConfig {
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub simulation: SimulationParameters,
+    #[serde(default)]
     pub agent_parameters: AgentParameters,
+    #[serde(default)]
     pub payoffs: PayoffScores,
+    pub visits: Option<VisitParameters>,
+    pub host: Option<HostParameters>,
+    pub capacity: Option<CapacityParameters>,
+    pub turnover: Option<TurnoverParameters>,
+    #[serde(default)]
+    pub population_events: Vec<PopulationEvent>,
+    pub energy: Option<EnergyParameters>,
+    pub ranking: Option<RankingParameters>,
+    pub initial_scores: Option<ScoreDistribution>,
+    pub contest_effects: Option<ContestEffects>,
+    pub sweep: Option<SweepParameters>,
+    #[serde(default)]
     pub csv: CSVFiles,
+    #[serde(default)]
+    pub recording: RecordingParameters,
 }
 
 pub struct InteractionTracker {
+    pub visitors: u64,
+    pub games: u64,
     pub hawk_hawk: u64,
     pub hawk_dove: u64,
     pub dove_hawk: u64,
     pub dove_dove: u64,
+    pub refused: u64,
+    pub rejected: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -91,249 +527,1928 @@ pub struct AgentInteractionTracker {
     pub dove_dove: u64,
 }
 
-impl RootConfig {
-    fn new(source_file: &str) -> Result<Self, ConfigError> {
-        let s = Config::builder()
-            .add_source(File::with_name(&format!("./Input/{}", source_file)))
-            .build()?;
-        s.try_deserialize()
-    }
+#[derive(Clone, Copy)]
+pub struct DyadRecord {
+    pub hawk_hawk: u64,
+    pub hawk_dove: u64,
+    pub dove_hawk: u64,
+    pub dove_dove: u64,
+    pub refused: u64,
+    pub visitor_payoff: f64,
+    pub host_payoff: f64,
+}
+
+/// Visitor x host matrix of strategy-pair counts and payoff sums.
+pub struct DyadTracker(pub Vec<Vec<DyadRecord>>);
+
+/// Interaction counts kept across the steps of one seed.
+pub struct Trackers {
+    pub agents: Vec<AgentInteractionTracker>,
+    pub dyads: Option<DyadTracker>,
+}
+
+/// A problem found in a config file, located by its TOML key path.
+#[derive(Debug)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{}: {}", self.key, self.reason)
+        }
+    }
+}
+
+impl From<ConfigError> for ConfigIssue {
+    fn from(error: ConfigError) -> ConfigIssue {
+        match &error {
+            ConfigError::Type {
+                key: Some(key),
+                unexpected,
+                expected,
+                ..
+            } => ConfigIssue {
+                key: key_path(key),
+                reason: format!("invalid type: {}, expected {}", unexpected, expected),
+            },
+            ConfigError::At {
+                error,
+                key: Some(key),
+                ..
+            } => {
+                let message = error.to_string();
+                match missing_field(&message) {
+                    Some(field) => ConfigIssue {
+                        key: format!("{}.{}", key_path(key), field),
+                        reason: "missing".to_string(),
+                    },
+                    None => ConfigIssue {
+                        key: key_path(key),
+                        reason: message,
+                    },
+                }
+            }
+            ConfigError::NotFound(key) => ConfigIssue {
+                key: key_path(key),
+                reason: "missing".to_string(),
+            },
+            _ => {
+                let message = error.to_string();
+                match missing_field(&message) {
+                    Some(field) => ConfigIssue {
+                        key: field.to_string(),
+                        reason: "missing".to_string(),
+                    },
+                    None => ConfigIssue {
+                        key: String::new(),
+                        reason: message,
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn collect_defaults(
+    prefix: &str,
+    effective: &Map<String, Value>,
+    provided: &Map<String, Value>,
+    keys: &mut Vec<String>,
+) {
+    for (key, value) in effective {
+        match &value.kind {
+            ValueKind::Nil => continue,
+            ValueKind::Array(values) if values.is_empty() => continue,
+            _ => {}
+        }
+
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match (&value.kind, provided.get(key).map(|value| &value.kind)) {
+            (_, None) => keys.push(path),
+            (ValueKind::Table(effective), Some(ValueKind::Table(provided))) => {
+                collect_defaults(&path, effective, provided, keys)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `key` with the dot `config` leaves out after an array index.
+fn key_path(key: &str) -> String {
+    let mut path = String::with_capacity(key.len() + 1);
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        path.push(c);
+        if c == ']' && chars.peek().is_some_and(|next| !matches!(next, '.' | '[')) {
+            path.push('.');
+        }
+    }
+    path
+}
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn segments(key: &str) -> Vec<Segment<'_>> {
+    let mut path = Vec::new();
+    for part in key.split('.') {
+        let mut pieces = part.split('[');
+        if let Some(name) = pieces.next().filter(|name| !name.is_empty()) {
+            path.push(Segment::Key(name));
+        }
+        path.extend(
+            pieces
+                .filter_map(|index| index.trim_end_matches(']').parse().ok())
+                .map(Segment::Index),
+        );
+    }
+    path
+}
+
+fn join(path: &[Segment]) -> String {
+    let mut key = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(name) if key.is_empty() => key.push_str(name),
+            Segment::Key(name) => key.push_str(&format!(".{}", name)),
+            Segment::Index(index) => key.push_str(&format!("[{}]", index)),
+        }
+    }
+    key
+}
+
+/// Removes `key`, or the nearest entry above it, and returns what it removed.
+fn remove_key(root: &mut Value, key: &str) -> Option<String> {
+    let mut path = segments(key);
+    while !path.is_empty() {
+        if remove_path(root, &path) {
+            return Some(join(&path));
+        }
+        path.pop();
+    }
+    None
+}
+
+fn remove_path(value: &mut Value, path: &[Segment]) -> bool {
+    match (path, &mut value.kind) {
+        ([Segment::Key(key)], ValueKind::Table(table)) => table.remove(*key).is_some(),
+        ([Segment::Index(index)], ValueKind::Array(array)) if *index < array.len() => {
+            array.remove(*index);
+            true
+        }
+        ([Segment::Key(key), rest @ ..], ValueKind::Table(table)) => table
+            .get_mut(*key)
+            .is_some_and(|inner| remove_path(inner, rest)),
+        ([Segment::Index(index), rest @ ..], ValueKind::Array(array)) => array
+            .get_mut(*index)
+            .is_some_and(|inner| remove_path(inner, rest)),
+        _ => false,
+    }
+}
+
+/// `key` indexed as before the `removed` array elements were dropped.
+fn original_key(key: &str, removed: &[String]) -> String {
+    let mut path = String::new();
+    for segment in segments(key) {
+        match segment {
+            Segment::Key(name) if path.is_empty() => path.push_str(name),
+            Segment::Key(name) => path.push_str(&format!(".{}", name)),
+            Segment::Index(index) => {
+                let original = (0..)
+                    .filter(|i| !removed.contains(&format!("{}[{}]", path, i)))
+                    .nth(index)
+                    .unwrap_or(index);
+                path.push_str(&format!("[{}]", original));
+            }
+        }
+    }
+    path
+}
+
+fn is_integer(value: &Value) -> bool {
+    matches!(
+        value.kind,
+        ValueKind::I64(_) | ValueKind::I128(_) | ValueKind::U64(_) | ValueKind::U128(_)
+    )
+}
+
+/// Field name out of serde's "missing field `name`" message.
+fn missing_field(message: &str) -> Option<&str> {
+    message.strip_prefix("missing field `")?.strip_suffix('`')
+}
+
+fn in_unit_interval(value: f64) -> bool {
+    (0.0..=1.0).contains(&value)
+}
+
+impl SweepAxis {
+    /// Rounds away the floating point drift of stepping through a range.
+    fn tidy(value: f64) -> f64 {
+        (value * 1e10).round() / 1e10
+    }
+
+    pub fn expand(&self) -> Result<Vec<SweepValue>, ConfigError> {
+        match &self.values {
+            SweepValues::Values(values) => Ok(values.clone()),
+            SweepValues::Range { start, stop, step } => {
+                if *step <= 0.0 || stop < start {
+                    return Err(ConfigError::Message(format!(
+                        "sweep over {}: range needs start <= stop and a positive step",
+                        self.key
+                    )));
+                }
+                let steps = ((stop - start) / step + 1e-9).floor() as u64;
+                Ok((0..=steps)
+                    .map(|k| SweepValue::Float(Self::tidy(start + k as f64 * step)))
+                    .collect())
+            }
+            SweepValues::LogRange { start, stop, num } => {
+                if *start <= 0.0 || *stop <= 0.0 || *num == 0 {
+                    return Err(ConfigError::Message(format!(
+                        "sweep over {}: log_range needs positive bounds and at least one value",
+                        self.key
+                    )));
+                }
+                if *num == 1 {
+                    return Ok(vec![SweepValue::Float(*start)]);
+                }
+                let ratio = (stop / start).powf(1.0 / (*num - 1) as f64);
+                Ok((0..*num)
+                    .map(|k| SweepValue::Float(Self::tidy(start * ratio.powi(k as i32))))
+                    .collect())
+            }
+        }
+    }
+}
+
+impl SweepParameters {
+    /// Every parameter combination of the sweep as `(key, value)` overrides.
+    pub fn combinations(&self) -> Result<Vec<Vec<(String, SweepValue)>>, ConfigError> {
+        let axes: Vec<Vec<SweepValue>> = self
+            .parameters
+            .iter()
+            .map(|axis| axis.expand())
+            .collect::<Result<_, _>>()?;
+
+        match self.mode {
+            SweepMode::Cartesian => {
+                let mut combos: Vec<Vec<(String, SweepValue)>> = vec![Vec::new()];
+                for (axis, values) in self.parameters.iter().zip(&axes) {
+                    combos = combos
+                        .iter()
+                        .flat_map(|combo| {
+                            values.iter().map(move |value| {
+                                let mut combo = combo.clone();
+                                combo.push((axis.key.clone(), value.clone()));
+                                combo
+                            })
+                        })
+                        .collect();
+                }
+                Ok(combos)
+            }
+            SweepMode::Zip => {
+                let len = axes.first().map_or(0, |values| values.len());
+                if axes.iter().any(|values| values.len() != len) {
+                    return Err(ConfigError::Message(
+                        "zipped sweep parameters must all have the same number of values"
+                            .to_string(),
+                    ));
+                }
+                Ok((0..len)
+                    .map(|j| {
+                        self.parameters
+                            .iter()
+                            .zip(&axes)
+                            .map(|(axis, values)| (axis.key.clone(), values[j].clone()))
+                            .collect()
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+impl RootConfig {
+    /// Config file over every `defaults.toml` above it, under env and `--set` overrides.
+    fn builder(
+        source_file: &str,
+        paths: &Paths,
+        overrides: &Overrides,
+    ) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+        let mut builder = Config::builder();
+
+        let mut directory = paths.input.clone();
+        builder = builder.add_source(File::from(directory.join(DEFAULTS_FILE)).required(false));
+        if let Some(parent) = Path::new(source_file.trim_start_matches('/')).parent() {
+            for component in parent.components() {
+                directory.push(component);
+                builder =
+                    builder.add_source(File::from(directory.join(DEFAULTS_FILE)).required(false));
+            }
+        }
+
+        builder = builder
+            .add_source(File::from(paths.input_file(source_file)))
+            .add_source(
+                Environment::with_prefix(ENVIRONMENT_PREFIX)
+                    .prefix_separator(ENVIRONMENT_SEPARATOR)
+                    .separator(ENVIRONMENT_SEPARATOR),
+            );
+        for Override { key, value } in &overrides.set {
+            builder = builder.set_override(key.as_str(), value.as_str())?;
+        }
+
+        Ok(builder)
+    }
+
+    fn new(source_file: &str, paths: &Paths, overrides: &Overrides) -> Result<Self, ConfigError> {
+        let s = Self::builder(source_file, paths, overrides)?.build()?;
+        let mut config: RootConfig = s.try_deserialize()?;
+        config.default_output_directory(source_file);
+
+        Ok(config)
+    }
+
+    fn default_output_directory(&mut self, source_file: &str) {
+        if self.simulation.output_directory.is_empty() {
+            self.simulation.output_directory = Path::new(source_file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+    }
+
+    /// Every serde and validation issue of a config that fails to deserialize.
+    fn read_lenient(source_file: &str, paths: &Paths, overrides: &Overrides) -> Vec<ConfigIssue> {
+        let table: Result<Map<String, Value>, ConfigError> =
+            Self::builder(source_file, paths, overrides)
+                .and_then(|builder| builder.build())
+                .and_then(|config| config.try_deserialize());
+        let mut root = match table {
+            Ok(table) => Value::new(None, ValueKind::Table(table)),
+            Err(e) => return vec![ConfigIssue::from(e)],
+        };
+        let mut issues: Vec<ConfigIssue> = Vec::new();
+        let mut removed_elements: Vec<String> = Vec::new();
+
+        let mut config = loop {
+            match root.clone().try_deserialize::<RootConfig>() {
+                Ok(config) => break config,
+                Err(e) => {
+                    let mut issue = ConfigIssue::from(e);
+                    let removed = remove_key(&mut root, &issue.key);
+                    issue.key = original_key(&issue.key, &removed_elements);
+                    if let Some(removed) = removed.as_ref().filter(|key| key.ends_with(']')) {
+                        removed_elements.push(original_key(removed, &removed_elements));
+                    }
+                    // Dropping a required field reports it again as missing.
+                    if !issues.iter().any(|reported| reported.key == issue.key) {
+                        issues.push(issue);
+                    }
+                    if removed.is_none() {
+                        return issues;
+                    }
+                }
+            }
+        };
+        config.default_output_directory(source_file);
+
+        let rejected: Vec<String> = issues.iter().map(|issue| issue.key.clone()).collect();
+        issues.extend(
+            config
+                .validate(paths)
+                .into_iter()
+                .map(|issue| ConfigIssue {
+                    key: original_key(&issue.key, &removed_elements),
+                    ..issue
+                })
+                .filter(|issue| {
+                    !rejected
+                        .iter()
+                        .any(|key| issue.key == *key || issue.key.starts_with(&format!("{}.", key)))
+                }),
+        );
+        issues
+    }
+
+    /// Keys filled in from serde defaults rather than read from a file.
+    fn applied_defaults(
+        &self,
+        source_file: &str,
+        paths: &Paths,
+        overrides: &Overrides,
+    ) -> Result<Vec<String>, ConfigError> {
+        let provided: Map<String, Value> = Self::builder(source_file, paths, overrides)?
+            .build()?
+            .try_deserialize()?;
+        let effective: Map<String, Value> = Config::try_from(self)?.try_deserialize()?;
+
+        let mut keys = Vec::new();
+        collect_defaults("", &effective, &provided, &mut keys);
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// One config per combination of the `[sweep]` section.
+    fn expand_sweep(
+        self,
+        source_file: &str,
+        paths: &Paths,
+        overrides: &Overrides,
+    ) -> Result<Vec<Self>, Vec<ConfigIssue>> {
+        let Some(sweep) = &self.sweep else {
+            return Ok(vec![self]);
+        };
+
+        let mut configs: Vec<Self> = Vec::new();
+        let mut resolved_configs: Vec<(serde_json::Value, String)> = Vec::new();
+        for combo in sweep
+            .combinations()
+            .map_err(|e| vec![ConfigIssue::from(e)])?
+        {
+            let mut builder =
+                Self::builder(source_file, paths, overrides).map_err(|e| vec![e.into()])?;
+            for (key, value) in &combo {
+                builder = builder
+                    .set_override(key.as_str(), value.clone())
+                    .map_err(|e| vec![e.into()])?;
+            }
+            let mut config: RootConfig = builder
+                .build()
+                .and_then(|config| config.try_deserialize())
+                .map_err(|e| vec![e.into()])?;
+            config.sweep = None;
+
+            // Label the run with the values it resolved to, which for integer
+            // fields may be a rounded float.
+            let fields = Config::try_from(&config).map_err(|e| vec![e.into()])?;
+            let mut issues = Vec::new();
+            let mut resolved = Vec::new();
+            for (j, (key, value)) in combo.iter().enumerate() {
+                match fields.get::<Value>(key) {
+                    Ok(field) if is_integer(&field) => resolved.push((key, field.to_string())),
+                    Ok(_) => resolved.push((key, value.to_string())),
+                    Err(_) => issues.push(ConfigIssue {
+                        key: format!("sweep.parameters[{}].key", j),
+                        reason: format!("`{}` is not a config key", key),
+                    }),
+                }
+            }
+            if !issues.is_empty() {
+                return Err(issues);
+            }
+
+            let label = resolved
+                .iter()
+                .map(|(key, value)| {
+                    let name = key.rsplit('.').next().unwrap_or(key);
+                    format!("{}_{}", name, value)
+                })
+                .collect::<Vec<_>>()
+                .join("_");
+            let settings = resolved
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let requested = combo
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let resolved_config = serde_json::to_value(&config)
+                .map_err(|e| vec![ConfigError::Message(e.to_string()).into()])?;
+            if let Some((_, earlier)) = resolved_configs.iter().find(|(c, _)| *c == resolved_config)
+            {
+                return Err(vec![ConfigIssue {
+                    key: "sweep.parameters".to_string(),
+                    reason: format!("{} and {} resolve to the same config", earlier, requested),
+                }]);
+            }
+            resolved_configs.push((resolved_config, requested));
+
+            config.simulation.output_directory =
+                format!("{}/{}", self.simulation.output_directory, label);
+            config.description = format!("{} ({})", self.description, settings);
+            configs.push(config);
+        }
+
+        Ok(configs)
+    }
+
+    /// Agent IDs needed for the initial population and every immigrant.
+    pub fn max_agents(&self) -> usize {
+        let immigrants: u32 = self
+            .population_events
+            .iter()
+            .filter(|event| event.action == PopulationAction::Immigrate)
+            .map(|event| event.count)
+            .sum();
+
+        (self.simulation.population + immigrants) as usize
+    }
+
+    /// Visit settings, one single-round visit per step by default.
+    pub fn visits(&self) -> VisitParameters {
+        self.visits.unwrap_or(VisitParameters {
+            per_step: default_per_step(),
+            count: VisitCount::Fixed,
+            rounds: default_rounds(),
+        })
+    }
+
+    /// Checks the values serde cannot.
+    pub fn validate(&self, paths: &Paths) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut check = |ok: bool, key: &str, reason: String| {
+            if !ok {
+                issues.push(ConfigIssue {
+                    key: key.to_string(),
+                    reason,
+                });
+            }
+        };
+
+        let sim = &self.simulation;
+        check(
+            sim.seeds >= 1,
+            "simulation.seeds",
+            "must be at least 1".to_string(),
+        );
+        check(
+            sim.population >= 2,
+            "simulation.population",
+            format!("must be at least 2, got {}", sim.population),
+        );
+        check(
+            !sim.output_directory.is_empty()
+                && !sim.output_directory.split('/').any(|part| part == ".."),
+            "simulation.output_directory",
+            format!(
+                "must be a non-empty path inside the output root, got {:?}",
+                sim.output_directory
+            ),
+        );
+
+        let agent = &self.agent_parameters;
+        for (key, value) in [
+            (
+                "agent_parameters.strat_learning_speed",
+                agent.strat_learning_speed,
+            ),
+            (
+                "agent_parameters.net_learning_speed",
+                agent.net_learning_speed,
+            ),
+        ] {
+            check(
+                value >= 0.0,
+                key,
+                format!("must not be negative, got {}", value),
+            );
+        }
+        for (key, value) in [
+            ("agent_parameters.strat_discount", agent.strat_discount),
+            ("agent_parameters.net_discount", agent.net_discount),
+            ("agent_parameters.strat_tremble", agent.strat_tremble),
+            ("agent_parameters.net_tremble", agent.net_tremble),
+        ] {
+            check(
+                in_unit_interval(value),
+                key,
+                format!("must be in [0, 1], got {}", value),
+            );
+        }
+
+        for (key, value) in [
+            ("payoffs.hd", self.payoffs.hd),
+            ("payoffs.dh", self.payoffs.dh),
+            ("payoffs.dd", self.payoffs.dd),
+            ("payoffs.hh_f", self.payoffs.hh_f),
+        ] {
+            check(
+                value.is_finite(),
+                key,
+                format!("must be a finite number, got {}", value),
+            );
+        }
+
+        if let Some(visits) = &self.visits {
+            check(
+                visits.per_step >= 1,
+                "visits.per_step",
+                "must be at least 1".to_string(),
+            );
+            check(
+                visits.rounds >= 1,
+                "visits.rounds",
+                "must be at least 1".to_string(),
+            );
+        }
+
+        if let Some(host) = &self.host {
+            check(
+                host.acceptance_learning_speed >= 0.0,
+                "host.acceptance_learning_speed",
+                format!(
+                    "must not be negative, got {}",
+                    host.acceptance_learning_speed
+                ),
+            );
+        }
+
+        if let Some(capacity) = &self.capacity {
+            check(
+                capacity.host_capacity >= 1,
+                "capacity.host_capacity",
+                "must be at least 1".to_string(),
+            );
+        }
+
+        if let Some(turnover) = &self.turnover {
+            check(
+                turnover.interval >= 1,
+                "turnover.interval",
+                "must be at least 1".to_string(),
+            );
+            check(
+                turnover.deaths < sim.population,
+                "turnover.deaths",
+                format!(
+                    "must be less than the population of {}, got {}",
+                    sim.population, turnover.deaths
+                ),
+            );
+            check(
+                turnover.mutation >= 0.0,
+                "turnover.mutation",
+                format!("must not be negative, got {}", turnover.mutation),
+            );
+        }
+
+        for (j, event) in self.population_events.iter().enumerate() {
+            // Events run at the start of a step, and the first step is 1.
+            check(
+                (1..=sim.max_time_step).contains(&event.step),
+                &format!("population_events[{}].step", j),
+                format!("must be in [1, {}], got {}", sim.max_time_step, event.step),
+            );
+        }
+
+        if let Some(energy) = &self.energy {
+            check(
+                energy.visit_cost >= 0.0,
+                "energy.visit_cost",
+                format!("must not be negative, got {}", energy.visit_cost),
+            );
+            check(
+                energy.fight_cost >= 0.0,
+                "energy.fight_cost",
+                format!("must not be negative, got {}", energy.fight_cost),
+            );
+        }
+
+        if let Some(ranking) = &self.ranking {
+            check(
+                ranking.interval >= 1,
+                "ranking.interval",
+                "must be at least 1".to_string(),
+            );
+            if ranking.mode == RankingMode::Exponential {
+                check(
+                    in_unit_interval(ranking.decay),
+                    "ranking.decay",
+                    format!("must be in [0, 1], got {}", ranking.decay),
+                );
+            }
+            if ranking.mode == RankingMode::Window {
+                check(
+                    ranking.window >= 1,
+                    "ranking.window",
+                    "must be at least 1".to_string(),
+                );
+            }
+            if ranking.mode == RankingMode::Elo {
+                check(
+                    ranking.elo_scale > 0.0,
+                    "ranking.elo_scale",
+                    format!("must be positive, got {}", ranking.elo_scale),
+                );
+            }
+        }
+
+        match &self.initial_scores {
+            Some(ScoreDistribution::Uniform { low, high }) => check(
+                low < high,
+                "initial_scores.high",
+                format!("must be greater than low ({}), got {}", low, high),
+            ),
+            Some(ScoreDistribution::Normal { sd, .. }) => check(
+                *sd > 0.0,
+                "initial_scores.sd",
+                format!("must be positive, got {}", sd),
+            ),
+            Some(ScoreDistribution::LogNormal { sigma, .. }) => check(
+                *sigma > 0.0,
+                "initial_scores.sigma",
+                format!("must be positive, got {}", sigma),
+            ),
+            Some(ScoreDistribution::PowerLaw { alpha, x_min }) => {
+                check(
+                    *alpha > 1.0,
+                    "initial_scores.alpha",
+                    format!("must be greater than 1, got {}", alpha),
+                );
+                check(
+                    *x_min > 0.0,
+                    "initial_scores.x_min",
+                    format!("must be positive, got {}", x_min),
+                );
+            }
+            Some(ScoreDistribution::File { path }) => check(
+                paths.input_file(path).is_file(),
+                "initial_scores.path",
+                format!("no score file at {}", paths.input_file(path).display()),
+            ),
+            Some(ScoreDistribution::Ranks) | None => {}
+        }
+
+        if let Some(contest_effects) = &self.contest_effects {
+            check(
+                in_unit_interval(contest_effects.decay),
+                "contest_effects.decay",
+                format!("must be in [0, 1], got {}", contest_effects.decay),
+            );
+        }
+
+        check(
+            self.csv.parquet.is_empty() || cfg!(feature = "parquet"),
+            "csv.parquet",
+            "this build does not include the `parquet` feature".to_string(),
+        );
+
+        for (name, schedule) in self.recording.schedules() {
+            match schedule {
+                RecordSchedule::Every(0) => check(
+                    false,
+                    &format!("recording.{}.every", name),
+                    "must be at least 1".to_string(),
+                ),
+                RecordSchedule::Log(0) => check(
+                    false,
+                    &format!("recording.{}.log", name),
+                    "must be at least 1".to_string(),
+                ),
+                _ => {}
+            }
+        }
+
+        issues
+    }
+
+    /// Score update policy, falling back to `simulation.dynamic_rank`.
+    pub fn ranking(&self) -> RankingParameters {
+        self.ranking.unwrap_or(RankingParameters {
+            mode: if self.simulation.dynamic_rank {
+                RankingMode::Cumulative
+            } else {
+                RankingMode::Fixed
+            },
+            interval: default_rank_interval(),
+            decay: default_rank_decay(),
+            window: 0,
+            k_factor: default_k_factor(),
+            elo_scale: default_elo_scale(),
+            initial_rating: default_initial_rating(),
+        })
+    }
+}
+
+fn default_per_step() -> u32 {
+    1
+}
+
+fn default_rounds() -> u32 {
+    1
+}
+
+fn default_rank_interval() -> u64 {
+    1000
+}
+
+fn default_rank_decay() -> f64 {
+    0.9
+}
+
+fn default_k_factor() -> f64 {
+    32.0
+}
+
+fn default_elo_scale() -> f64 {
+    400.0
+}
+
+fn default_initial_rating() -> f64 {
+    1500.0
+}
+
+impl PopulationAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PopulationAction::Immigrate => "immigrate",
+            PopulationAction::RemoveTop => "remove_top",
+            PopulationAction::RemoveRandom => "remove_random",
+            PopulationAction::Starved => "starved",
+        }
+    }
+}
+
+impl InteractionTracker {
+    pub fn new() -> InteractionTracker {
+        InteractionTracker {
+            visitors: 0,
+            games: 0,
+            hawk_hawk: 0,
+            hawk_dove: 0,
+            dove_hawk: 0,
+            dove_dove: 0,
+            refused: 0,
+            rejected: 0,
+        }
+    }
+
+    pub fn default(pop: usize) -> InteractionTracker {
+        InteractionTracker {
+            visitors: pop as u64,
+            games: pop as u64,
+            hawk_hawk: pop as u64 / 4,
+            hawk_dove: pop as u64 / 4,
+            dove_hawk: pop as u64 / 4,
+            dove_dove: pop as u64 / 4,
+            refused: 0,
+            rejected: 0,
+        }
+    }
+}
+
+impl AgentInteractionTracker {
+    pub fn new() -> AgentInteractionTracker {
+        AgentInteractionTracker {
+            hawk_hawk: 0,
+            hawk_dove: 0,
+            dove_hawk: 0,
+            dove_dove: 0,
+        }
+    }
+}
+
+impl DyadRecord {
+    pub fn new() -> DyadRecord {
+        DyadRecord {
+            hawk_hawk: 0,
+            hawk_dove: 0,
+            dove_hawk: 0,
+            dove_dove: 0,
+            refused: 0,
+            visitor_payoff: 0.0,
+            host_payoff: 0.0,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.hawk_hawk + self.hawk_dove + self.dove_hawk + self.dove_dove + self.refused
+    }
+}
+
+impl Trackers {
+    pub fn new(max_agents: usize, dyads: bool) -> Trackers {
+        Trackers {
+            agents: vec![AgentInteractionTracker::new(); max_agents],
+            dyads: dyads.then(|| DyadTracker::new(max_agents)),
+        }
+    }
+}
+
+impl DyadTracker {
+    pub fn new(pop: usize) -> DyadTracker {
+        DyadTracker(vec![vec![DyadRecord::new(); pop]; pop])
+    }
+
+    pub fn record(
+        &mut self,
+        visitor: AgentId,
+        host: AgentId,
+        strategies: (Strategy, Strategy),
+        visitor_payoff: f64,
+        host_payoff: f64,
+    ) {
+        let dyad = &mut self.0[visitor.0 as usize][host.0 as usize];
+
+        match strategies {
+            (Strategy::Hawk, Strategy::Hawk) => dyad.hawk_hawk += 1,
+            (Strategy::Hawk, Strategy::Dove) => dyad.hawk_dove += 1,
+            (Strategy::Dove, Strategy::Hawk) => dyad.dove_hawk += 1,
+            (Strategy::Dove, Strategy::Dove) => dyad.dove_dove += 1,
+            _ => unreachable!(),
+        }
+
+        dyad.visitor_payoff += visitor_payoff;
+        dyad.host_payoff += host_payoff;
+    }
+
+    pub fn record_refusal(
+        &mut self,
+        visitor: AgentId,
+        host: AgentId,
+        visitor_payoff: f64,
+        host_payoff: f64,
+    ) {
+        let dyad = &mut self.0[visitor.0 as usize][host.0 as usize];
+
+        dyad.refused += 1;
+        dyad.visitor_payoff += visitor_payoff;
+        dyad.host_payoff += host_payoff;
+    }
+
+    pub fn reset_agent(&mut self, agent_id: AgentId) {
+        let index = agent_id.0 as usize;
+
+        for dyad in self.0[index].iter_mut() {
+            *dyad = DyadRecord::new();
+        }
+
+        for row in self.0.iter_mut() {
+            row[index] = DyadRecord::new();
+        }
+    }
+}
+
+/// Root directories configs are read from and results written to.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+impl Paths {
+    /// A config or score file given relative to the input root.
+    pub fn input_file(&self, file: &str) -> PathBuf {
+        self.input.join(file.trim_start_matches('/'))
+    }
+
+    /// Directory for the results of a run, `{output}/{group}/{output_directory}`.
+    pub fn run_directory(&self, group: &str, output_directory: &str) -> String {
+        self.output
+            .join(group)
+            .join(output_directory)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+pub const ENVIRONMENT_PREFIX: &str = "THESIS";
+pub const ENVIRONMENT_SEPARATOR: &str = "__";
+
+/// A single config key replaced on the command line or from the environment.
+#[derive(Debug, Clone, Serialize)]
+pub struct Override {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    /// Parses `section.key=value`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Override {
+                key: key.trim().to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("expected KEY=VALUE, got `{}`", s)),
+        }
+    }
+}
+
+/// Config keys replaced on top of the config files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Overrides {
+    /// `THESIS__SECTION__KEY` environment variables, as config keys.
+    pub environment: Vec<Override>,
+    /// `--set` arguments, applied after the environment.
+    pub set: Vec<Override>,
+}
+
+impl Overrides {
+    pub fn new(set: Vec<Override>) -> Self {
+        let prefix = format!("{}{}", ENVIRONMENT_PREFIX, ENVIRONMENT_SEPARATOR);
+        let mut environment: Vec<Override> = std::env::vars()
+            .filter_map(|(name, value)| {
+                let key = name.strip_prefix(&prefix)?;
+                Some(Override {
+                    key: key
+                        .to_lowercase()
+                        .split(ENVIRONMENT_SEPARATOR)
+                        .collect::<Vec<_>>()
+                        .join("."),
+                    value,
+                })
+            })
+            .collect();
+        environment.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Overrides { environment, set }
+    }
+}
+
+static VERBOSITY: AtomicI8 = AtomicI8::new(0);
+
+/// -1 prints only errors, 0 the usual progress and warnings, 1 and up more.
+pub fn set_verbosity(level: i8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> i8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Shared settings layered underneath the config files next to and below it.
+pub const DEFAULTS_FILE: &str = "defaults.toml";
+
+/// `(source_file, group)` of each config file named by `input`.
+pub fn get_config_sources(input: &str, paths: &Paths) -> Vec<(String, String)> {
+    let input = input.trim_matches('/');
+
+    if paths.input_file(input).is_file() {
+        let group = Path::new(input)
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        return vec![(input.to_string(), group)];
+    }
+
+    get_config_files(input, paths)
+        .into_iter()
+        .map(|file| {
+            let group = match Path::new(&file).parent() {
+                Some(parent) => Path::new(input).join(parent),
+                None => PathBuf::from(input),
+            };
+            (
+                format!("{}/{}", input, file),
+                group.to_string_lossy().into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Config files below `path` in the input root, relative to it.
+pub fn get_config_files(path: &str, paths: &Paths) -> Vec<String> {
+    let full_path = paths.input_file(path);
+
+    WalkDir::new(&full_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "toml"))
+        .filter(|e| e.file_name() != DEFAULTS_FILE)
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(&full_path)
+                .ok()
+                .map(|file| file.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Reads a config file into its validated sweep runs.
+pub fn read_config_file(
+    source_file: &str,
+    paths: &Paths,
+    overrides: &Overrides,
+) -> Result<Vec<RootConfig>, Vec<ConfigIssue>> {
+    let config = match RootConfig::new(source_file, paths, overrides) {
+        Ok(config) => config,
+        Err(_) => {
+            // Report the values serde rejected together with everything the
+            // rest of the config fails in `validate`.
+            let mut issues = RootConfig::read_lenient(source_file, paths, overrides);
+            issues.sort_by(|a, b| a.key.cmp(&b.key));
+            return Err(issues);
+        }
+    };
+
+    match config.applied_defaults(source_file, paths, overrides) {
+        Ok(keys) if !keys.is_empty() && verbosity() >= 0 => {
+            eprintln!(
+                "Warning: {}: using defaults for {}",
+                paths.input_file(source_file).display(),
+                keys.join(", ")
+            );
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!(
+            "Warning: {}: could not list applied defaults: {}",
+            paths.input_file(source_file).display(),
+            e
+        ),
+    }
+    if config.simulation.dynamic_rank && config.ranking.is_some() && verbosity() >= 0 {
+        eprintln!(
+            "Warning: {}: simulation.dynamic_rank is ignored when [ranking] is set",
+            paths.input_file(source_file).display()
+        );
+    }
+
+    let fields = Config::try_from(&config).map_err(|e| vec![e.into()])?;
+    let unknown: Vec<ConfigIssue> = overrides
+        .environment
+        .iter()
+        .map(|o| (o, "a THESIS__ environment variable"))
+        .chain(overrides.set.iter().map(|o| (o, "--set")))
+        .filter(|(o, _)| fields.get::<Value>(&o.key).is_err())
+        .map(|(o, source)| ConfigIssue {
+            key: o.key.clone(),
+            reason: format!("is not a config key (set by {})", source),
+        })
+        .collect();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+
+    let configs = config.expand_sweep(source_file, paths, overrides)?;
+
+    let sweep = configs.len() > 1;
+    let issues: Vec<ConfigIssue> = configs
+        .iter()
+        .flat_map(|config| {
+            config.validate(paths).into_iter().map(move |mut issue| {
+                if sweep {
+                    issue.reason = format!(
+                        "{} (in sweep run {})",
+                        issue.reason, config.simulation.output_directory
+                    );
+                }
+                issue
+            })
+        })
+        .collect();
+
+    if issues.is_empty() {
+        Ok(configs)
+    } else {
+        Err(issues)
+    }
+}
+
+pub fn report_config_issues(source_file: &str, issues: &[ConfigIssue], paths: &Paths) {
+    for issue in issues {
+        eprintln!("{}: {}", paths.input_file(source_file).display(), issue);
+    }
+}
+
+/// Checks every config file under `path`, returning the number with problems.
+pub fn validate_config_files(path: &str, paths: &Paths, overrides: &Overrides) -> usize {
+    let sources = get_config_sources(path, paths);
+    if sources.is_empty() {
+        eprintln!(
+            "Error: no config files found at {}",
+            paths.input_file(path).display()
+        );
+        return 1;
+    }
+
+    let mut invalid = 0;
+
+    for (source_file, _) in sources {
+        match read_config_file(&source_file, paths, overrides) {
+            Ok(configs) => {
+                if verbosity() >= 0 {
+                    println!(
+                        "{}: ok ({} runs)",
+                        paths.input_file(&source_file).display(),
+                        configs.len()
+                    );
+                }
+            }
+            Err(issues) => {
+                report_config_issues(&source_file, &issues, paths);
+                invalid += 1;
+            }
+        }
+    }
+
+    invalid
+}
+
+/// The files a run can write, one per kind and seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    InitialScores,
+    Weights,
+    EvoStats,
+    StrategyVisit,
+    StrategyHost,
+    Scores,
+    NetStd,
+    OutScore,
+    Energy,
+    TotalPayoff,
+    TotalInteractions,
+    Dyads,
+    Turnover,
+    Population,
 }
 
-impl InteractionTracker {
-    pub fn new() -> InteractionTracker {
-        InteractionTracker {
-            hawk_hawk: 0,
-            hawk_dove: 0,
-            dove_hawk: 0,
-            dove_dove: 0,
+impl OutputKind {
+    pub const ALL: [OutputKind; 14] = [
+        OutputKind::InitialScores,
+        OutputKind::Weights,
+        OutputKind::EvoStats,
+        OutputKind::StrategyVisit,
+        OutputKind::StrategyHost,
+        OutputKind::Scores,
+        OutputKind::NetStd,
+        OutputKind::OutScore,
+        OutputKind::Energy,
+        OutputKind::TotalPayoff,
+        OutputKind::TotalInteractions,
+        OutputKind::Dyads,
+        OutputKind::Turnover,
+        OutputKind::Population,
+    ];
+
+    pub fn stem(self) -> &'static str {
+        match self {
+            OutputKind::InitialScores => "InitialScores",
+            OutputKind::Weights => "Weights",
+            OutputKind::EvoStats => "EvoStats",
+            OutputKind::StrategyVisit => "StrategyVisit",
+            OutputKind::StrategyHost => "StrategyHost",
+            OutputKind::Scores => "Scores",
+            OutputKind::NetStd => "NetSTD",
+            OutputKind::OutScore => "OutScore",
+            OutputKind::Energy => "Energy",
+            OutputKind::TotalPayoff => "TotalPayoff",
+            OutputKind::TotalInteractions => "TotalInteractions",
+            OutputKind::Dyads => "Dyads",
+            OutputKind::Turnover => "Turnover",
+            OutputKind::Population => "Population",
         }
     }
 
-    pub fn default(pop: usize) -> InteractionTracker {
-        InteractionTracker {
-            hawk_hawk: pop as u64 / 4,
-            hawk_dove: pop as u64 / 4,
-            dove_hawk: pop as u64 / 4,
-            dove_dove: pop as u64 / 4,
+    pub fn path(self, output_directory: &str, seed: u64, extension: &str) -> String {
+        format!(
+            "{}/{}_{}.{}",
+            output_directory,
+            self.stem(),
+            seed,
+            extension
+        )
+    }
+
+    /// The kind and seed of a file written by `path`, if it is one.
+    pub fn parse_file_name(name: &str) -> Option<(OutputKind, u64)> {
+        let (stem, seed) = name
+            .strip_suffix(".csv")
+            .or_else(|| name.strip_suffix(".parquet"))?
+            .rsplit_once('_')?;
+        let kind = OutputKind::ALL
+            .into_iter()
+            .find(|kind| kind.stem() == stem)?;
+        Some((kind, seed.parse().ok()?))
+    }
+
+    /// Role and recorded variables of per-agent outputs.
+    fn agent_variables(self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            OutputKind::InitialScores | OutputKind::Scores => Some(("", &["score"])),
+            OutputKind::StrategyVisit => Some(("visitor", &["hawk", "dove"])),
+            OutputKind::StrategyHost => Some(("host", &["hawk", "dove"])),
+            OutputKind::NetStd => Some(("host", &["incoming_weight"])),
+            OutputKind::OutScore => Some(("", &["rank"])),
+            OutputKind::Energy => Some(("", &["energy"])),
+            OutputKind::TotalPayoff => Some(("", &["total_payoff"])),
+            OutputKind::TotalInteractions => {
+                Some(("", &["hawk_hawk", "hawk_dove", "dove_hawk", "dove_dove"]))
+            }
+            _ => None,
         }
     }
-}
 
-impl AgentInteractionTracker {
-    pub fn new() -> AgentInteractionTracker {
-        AgentInteractionTracker {
-            hawk_hawk: 0,
-            hawk_dove: 0,
-            dove_hawk: 0,
-            dove_dove: 0,
+    /// Whether `format` changes the layout of this output.
+    fn has_long_format(self) -> bool {
+        self == OutputKind::Weights || self.agent_variables().is_some()
+    }
+
+    /// Header row of the file for a run with `agents` agent IDs.
+    pub fn header(self, agents: usize, format: CSVFormat) -> Vec<String> {
+        if format == CSVFormat::Long && self.has_long_format() {
+            return self
+                .columns(format)
+                .iter()
+                .map(|column| column.to_string())
+                .collect();
+        }
+
+        let mut header = vec!["step".to_string()];
+        match (self, self.agent_variables()) {
+            (OutputKind::Weights, _) => {
+                for visitor in 0..agents {
+                    header.extend((0..agents).map(|host| format!("weight_{}_{}", visitor, host)));
+                }
+            }
+            (_, Some((_, variables))) => {
+                for agent in 0..agents {
+                    header.extend(
+                        variables
+                            .iter()
+                            .map(|variable| format!("{}_{}", variable, agent)),
+                    );
+                }
+            }
+            _ => header.extend(
+                self.columns(format)[1..]
+                    .iter()
+                    .map(|column| column.to_string()),
+            ),
+        }
+        header
+    }
+
+    /// Column layout described for the manifest, `N` being the number of agent IDs.
+    pub fn columns(self, format: CSVFormat) -> &'static [&'static str] {
+        if format == CSVFormat::Long && self.has_long_format() {
+            return if self == OutputKind::Weights {
+                &LONG_WEIGHT_COLUMNS
+            } else {
+                &LONG_COLUMNS
+            };
+        }
+
+        match self {
+            OutputKind::InitialScores | OutputKind::Scores => {
+                &["step", "score_{agent} for agent in 0..N"]
+            }
+            OutputKind::Weights => &["step", "weight_{visitor}_{host} for visitor, host in 0..N"],
+            OutputKind::EvoStats => &[
+                "step",
+                "hawk_hawk",
+                "hawk_dove",
+                "dove_hawk",
+                "dove_dove",
+                "refused",
+                "rejected",
+            ],
+            OutputKind::StrategyVisit | OutputKind::StrategyHost => {
+                &["step", "hawk_{agent}, dove_{agent} for agent in 0..N"]
+            }
+            OutputKind::NetStd => &["step", "incoming_weight_{host} for host in 0..N"],
+            OutputKind::OutScore => &["step", "rank_{agent} for agent in 0..N"],
+            OutputKind::Energy => &["step", "energy_{agent} for agent in 0..N"],
+            OutputKind::TotalPayoff => &["step", "total_payoff_{agent} for agent in 0..N"],
+            OutputKind::TotalInteractions => &[
+                "step",
+                "hawk_hawk_{agent}, hawk_dove_{agent}, dove_hawk_{agent}, dove_dove_{agent} for agent in 0..N",
+            ],
+            OutputKind::Dyads => &[
+                "step",
+                "visitor",
+                "host",
+                "hawk_hawk",
+                "hawk_dove",
+                "dove_hawk",
+                "dove_dove",
+                "refused",
+                "visitor_payoff",
+                "host_payoff",
+            ],
+            OutputKind::Turnover => &["step", "agent", "parent", "generation", "score"],
+            OutputKind::Population => &["step", "agent", "action"],
         }
     }
 }
 
-pub fn get_config_files(path: &str) -> Vec<String> {
-    let full_path = format!("Input/{}", path);
+pub const LONG_COLUMNS: [&str; 5] = ["step", "agent", "role", "variable", "value"];
+pub const LONG_WEIGHT_COLUMNS: [&str; 4] = ["step", "visitor", "host", "weight"];
 
-    WalkDir::new(full_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.file_name().to_string_lossy().into_owned())
-        .collect()
+/// How often buffered rows are flushed while a run is going.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// An open output file in the format chosen for it in `csv.parquet`.
+pub enum OutputWriter {
+    Csv(Writer<std::fs::File>),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetWriter),
 }
 
-pub fn read_config_file(source_file: &String) -> RootConfig {
-    match RootConfig::new(source_file) {
-        Ok(config) => config,
-        Err(e) => {
-            eprintln!("Error loading simulation parameters: {}", e);
-            panic!("Failed to load simulation parameters");
+impl OutputWriter {
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<(), Box<dyn Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        match self {
+            OutputWriter::Csv(writer) => {
+                writer.write_record(record.into_iter().map(|field| field.as_ref().to_string()))?
+            }
+            #[cfg(feature = "parquet")]
+            OutputWriter::Parquet(writer) => writer.write_record(record)?,
         }
+        Ok(())
     }
-}
 
-pub fn delete_directories(output_directory: &str) -> io::Result<()> {
-    let path = format!("./Output/{}", output_directory);
-    match remove_dir_all(path) {
-        Ok(()) => Ok(()),
-        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
-        Err(e) => Err(e),
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        match self {
+            OutputWriter::Csv(writer) => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            OutputWriter::Parquet(writer) => writer.flush()?,
+        }
+        Ok(())
     }
-}
 
-pub fn create_directories(output_directory: &str) {
-    let path = format!("./Output/{}", output_directory);
-    match create_dir_all(path) {
-        Ok(()) => {}
-        Err(e) => {
-            eprintln!("Error making directory: {}", e);
+    fn close(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            OutputWriter::Csv(mut writer) => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            OutputWriter::Parquet(writer) => writer.close()?,
         }
+        Ok(())
     }
 }
 
-pub fn generate_weights_csv(
-    i: u64,
-    network: &Network,
-    output_directory: &str,
+/// Writers for the files of one seed, opened on first use.
+pub struct OutputManager {
+    output_directory: String,
     seed: u64,
-) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/Weights_{}.csv", output_directory, seed);
+    agents: usize,
+    csv: CSVFiles,
+    writers: HashMap<OutputKind, OutputWriter>,
+    last_flush: Instant,
+}
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+impl OutputManager {
+    pub fn new(output_directory: &str, seed: u64, agents: usize, csv: &CSVFiles) -> OutputManager {
+        OutputManager {
+            output_directory: output_directory.to_string(),
+            seed,
+            agents,
+            csv: csv.clone(),
+            writers: HashMap::new(),
+            last_flush: Instant::now(),
+        }
+    }
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+    fn format(&self, kind: OutputKind) -> CSVFormat {
+        self.csv.format(kind)
+    }
 
-    let linear_vec: Vec<f32> = network
-        .0
-        .iter()
-        .flat_map(|row| row.iter().cloned())
-        .map(|x| x as f32)
-        .collect();
+    fn path(&self, kind: OutputKind) -> String {
+        let extension = if self.csv.parquet.contains(&kind) {
+            "parquet"
+        } else {
+            "csv"
+        };
+        kind.path(&self.output_directory, self.seed, extension)
+    }
 
-    let mut string_vec: Vec<String> = linear_vec.iter().map(|x| x.to_string()).collect();
+    pub fn writer(&mut self, kind: OutputKind) -> Result<&mut OutputWriter, Box<dyn Error>> {
+        if !self.writers.contains_key(&kind) {
+            let filepath = self.path(kind);
+            let header = kind.header(self.agents, self.format(kind));
+            let writer = if self.csv.parquet.contains(&kind) {
+                self.open_parquet(&filepath, kind, &header)?
+            } else {
+                let file = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(&filepath)
+                    .map_err(|e| format!("opening {}: {}", filepath, e))?;
+                let empty = file.metadata()?.len() == 0;
+                let mut writer = WriterBuilder::new()
+                    .buffer_capacity(1 << 16)
+                    .from_writer(file);
+                if empty {
+                    writer.write_record(header)?;
+                }
+                OutputWriter::Csv(writer)
+            };
+            self.writers.insert(kind, writer);
+        }
 
-    string_vec.insert(0, i.to_string());
+        Ok(self
+            .writers
+            .get_mut(&kind)
+            .expect("writer was just inserted"))
+    }
 
-    writer.write_record(&string_vec)?;
+    #[cfg(feature = "parquet")]
+    fn open_parquet(
+        &self,
+        filepath: &str,
+        kind: OutputKind,
+        header: &[String],
+    ) -> Result<OutputWriter, Box<dyn Error>> {
+        let file =
+            std::fs::File::create(filepath).map_err(|e| format!("opening {}: {}", filepath, e))?;
+        Ok(OutputWriter::Parquet(ParquetWriter::new(
+            file, kind, header,
+        )?))
+    }
 
-    Ok(())
-}
+    #[cfg(not(feature = "parquet"))]
+    fn open_parquet(
+        &self,
+        filepath: &str,
+        _kind: OutputKind,
+        _header: &[String],
+    ) -> Result<OutputWriter, Box<dyn Error>> {
+        Err(format!(
+            "{}: this build does not include the `parquet` feature",
+            filepath
+        )
+        .into())
+    }
 
-pub fn generate_evostats_csv(
-    i: u64,
-    pop: usize,
-    interaction_tracker: &InteractionTracker,
-    output_directory: &str,
-    seed: u64,
-) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/EvoStats_{}.csv", output_directory, seed);
+    /// Writes one value per agent and variable, `None` for absent agents.
+    pub fn write_agents<T: ToString>(
+        &mut self,
+        kind: OutputKind,
+        step: u64,
+        agents: &[Agent],
+        values: impl Fn(usize, &Agent) -> Option<Vec<T>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (role, variables) = kind
+            .agent_variables()
+            .ok_or_else(|| format!("{} is not a per-agent output", kind.stem()))?;
+        let format = self.format(kind);
+        let writer = self.writer(kind)?;
+
+        match format {
+            CSVFormat::Wide => {
+                let mut record = vec![step.to_string()];
+                for (id, agent) in agents.iter().enumerate() {
+                    match values(id, agent) {
+                        Some(values) => record.extend(values.iter().map(|value| value.to_string())),
+                        None => record.extend(variables.iter().map(|_| String::new())),
+                    }
+                }
+                writer.write_record(&record)?;
+            }
+            CSVFormat::Long => {
+                for (id, agent) in agents.iter().enumerate() {
+                    let Some(values) = values(id, agent) else {
+                        continue;
+                    };
+                    for (variable, value) in variables.iter().zip(values) {
+                        writer.write_record(&[
+                            step.to_string(),
+                            id.to_string(),
+                            role.to_string(),
+                            variable.to_string(),
+                            value.to_string(),
+                        ])?;
+                    }
+                }
+            }
+        }
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+        Ok(())
+    }
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+    /// Flushes every writer if `FLUSH_INTERVAL` has passed since the last flush.
+    pub fn end_step(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
 
-    let mut strat_stats = vec![i.to_string()];
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let kinds: Vec<OutputKind> = self.writers.keys().copied().collect();
+        for kind in kinds {
+            let path = self.path(kind);
+            if let Some(writer) = self.writers.get_mut(&kind) {
+                writer
+                    .flush()
+                    .map_err(|e| format!("writing {}: {}", path, e))?;
+            }
+        }
+        self.last_flush = Instant::now();
+        Ok(())
+    }
 
-    let hawk_hawk = (interaction_tracker.hawk_hawk as f64 / pop as f64).to_string();
-    strat_stats.push(hawk_hawk);
-    let hawk_dove = (interaction_tracker.hawk_dove as f64 / pop as f64).to_string();
-    strat_stats.push(hawk_dove);
-    let dove_hawk = (interaction_tracker.dove_hawk as f64 / pop as f64).to_string();
-    strat_stats.push(dove_hawk);
-    let dove_dove = (interaction_tracker.dove_dove as f64 / pop as f64).to_string();
-    strat_stats.push(dove_dove);
+    /// Flushes and closes every file, reporting errors from the final flush.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        for (kind, writer) in std::mem::take(&mut self.writers) {
+            writer
+                .close()
+                .map_err(|e| format!("writing {}: {}", self.path(kind), e))?;
+        }
+        Ok(())
+    }
+}
 
-    writer.write_record(&strat_stats)?;
+#[derive(Debug, Serialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub kind: OutputKind,
+    pub seed: u64,
+    pub columns: &'static [&'static str],
+}
+
+/// Provenance record written as `manifest.json` next to a run's results.
+#[derive(Debug, Serialize)]
+pub struct Manifest<'a> {
+    pub description: &'a str,
+    pub source_file: String,
+    pub config: &'a RootConfig,
+    pub overrides: &'a Overrides,
+    pub seeds: Vec<u64>,
+    pub agents: usize,
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub host: String,
+    pub started: String,
+    pub finished: String,
+    pub duration_seconds: f64,
+    pub files: Vec<ManifestFile>,
+}
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+pub fn list_output_files(output_directory: &str, csv: &CSVFiles) -> io::Result<Vec<ManifestFile>> {
+    let mut files: Vec<ManifestFile> = read_dir(output_directory)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (kind, seed) = OutputKind::parse_file_name(&name)?;
+            Some(ManifestFile {
+                name,
+                kind,
+                seed,
+                columns: kind.columns(csv.format(kind)),
+            })
+        })
+        .collect();
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
 
+pub fn write_manifest(output_directory: &str, manifest: &Manifest) -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::create(Path::new(output_directory).join(MANIFEST_FILE))?;
+    serde_json::to_writer_pretty(file, manifest)?;
     Ok(())
 }
 
-pub fn generate_strategyvisit_csv(
-    agents: &Vec<Agent>,
-    output_directory: &str,
-    seed: u64,
-) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/StrategyVisit_{}.csv", output_directory, seed);
+/// Name of the machine the simulation runs on, as far as it can be found.
+pub fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+pub fn read_score_file(source_file: &str, paths: &Paths) -> Result<Vec<f64>, Box<dyn Error>> {
+    let path = paths.input_file(source_file);
+    let file = std::fs::File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
 
-    let mut visit_vector: Vec<f64> = Vec::new();
+    let mut scores: Vec<f64> = Vec::new();
 
-    for i in 0..agents.len() {
-        let sum = agents[i].strategy.visit[0] + agents[i].strategy.visit[1];
-        visit_vector.push(agents[i].strategy.visit[0] / sum);
-        visit_vector.push(agents[i].strategy.visit[1] / sum);
+    for record in reader.records() {
+        for field in record?.iter() {
+            scores.push(field.trim().parse()?);
+        }
     }
 
-    let string_vec: Vec<String> = visit_vector.iter().map(|x| *x as f32).map(|x| x.to_string()).collect();
+    Ok(scores)
+}
+
+/// Name of the link in an output directory pointing at its most recent run.
+pub const LATEST_LINK: &str = "latest";
+
+/// Creates a new directory for one run named after `run_id`.
+pub fn create_run_directory(output_directory: &str, run_id: &str) -> io::Result<String> {
+    create_dir_all(output_directory)?;
+
+    let mut name = run_id.to_string();
+    let mut attempt = 1;
+    loop {
+        let path = Path::new(output_directory).join(&name);
+        match create_dir(&path) {
+            Ok(()) => return Ok(path.to_string_lossy().into_owned()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                name = format!("{}-{}", run_id, attempt);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
 
-    writer.write_record(string_vec)?;
+/// Empties the run `latest` points at, returning its path if there is one.
+pub fn reuse_latest_run(output_directory: &str) -> io::Result<Option<String>> {
+    let Ok(target) = read_link(Path::new(output_directory).join(LATEST_LINK)) else {
+        return Ok(None);
+    };
+    let run_directory = Path::new(output_directory).join(target);
+    if !run_directory.is_dir() {
+        return Ok(None);
+    }
 
-    Ok(())
+    remove_dir_all(&run_directory)?;
+    create_dir(&run_directory)?;
+    Ok(Some(run_directory.to_string_lossy().into_owned()))
 }
 
-pub fn generate_strategyhost_csv(
-    agents: &Vec<Agent>,
-    output_directory: &str,
-    seed: u64,
-) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/StrategyHost_{}.csv", output_directory, seed);
+/// Points `latest` at `run_directory` atomically.
+pub fn update_latest(output_directory: &str, run_directory: &str) -> io::Result<()> {
+    let Some(run_name) = Path::new(run_directory).file_name() else {
+        return Ok(());
+    };
+
+    let link = Path::new(output_directory).join(LATEST_LINK);
+    let staged = Path::new(output_directory).join(format!(
+        ".{}-{}",
+        LATEST_LINK,
+        run_name.to_string_lossy()
+    ));
+
+    symlink(run_name, &staged)?;
+    rename(&staged, &link)
+}
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+/// The most recent run in `directory`.
+pub fn latest_run(directory: &Path) -> Result<PathBuf, String> {
+    let latest = directory.join(LATEST_LINK);
+    if latest.exists() {
+        Ok(latest)
+    } else {
+        Err(format!("{}: no runs found", directory.display()))
+    }
+}
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+pub fn generate_initialscores_csv(
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    // Scores are drawn for every agent ID, including immigrants that join later.
+    output.write_agents(OutputKind::InitialScores, 0, agents, |_, agent| {
+        Some(vec![agent.score as f32])
+    })
+}
 
-    let mut host_vector: Vec<f64> = Vec::new();
+pub fn generate_weights_csv(
+    i: u64,
+    agents: &[Agent],
+    network: &Network,
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    let format = output.format(OutputKind::Weights);
+    let writer = output.writer(OutputKind::Weights)?;
 
-    for i in 0..agents.len() {
-        let sum = agents[i].strategy.host[0] + agents[i].strategy.host[1];
-        host_vector.push(agents[i].strategy.host[0] / sum);
-        host_vector.push(agents[i].strategy.host[1] / sum);
-    }
+    match format {
+        CSVFormat::Wide => {
+            let mut string_vec: Vec<String> = network
+                .0
+                .iter()
+                .flat_map(|row| row.iter())
+                .map(|x| (*x as f32).to_string())
+                .collect();
 
-    let string_vec: Vec<String> = host_vector.iter().map(|x| *x as f32).map(|x| x.to_string()).collect();
+            string_vec.insert(0, i.to_string());
 
-    writer.write_record(string_vec)?;
+            writer.write_record(&string_vec)?;
+        }
+        CSVFormat::Long => {
+            for (visitor, row) in network.0.iter().enumerate() {
+                if !agents[visitor].is_active() {
+                    continue;
+                }
+                for (host, weight) in row.iter().enumerate() {
+                    if !agents[host].is_active() {
+                        continue;
+                    }
+                    writer.write_record(&[
+                        i.to_string(),
+                        visitor.to_string(),
+                        host.to_string(),
+                        (*weight as f32).to_string(),
+                    ])?;
+                }
+            }
+        }
+    }
 
     Ok(())
 }
 
-pub fn generate_scores_csv(
-    agents: &Vec<Agent>,
-    output_directory: &str,
-    seed: u64,
+pub fn generate_evostats_csv(
+    i: u64,
+    interaction_tracker: &InteractionTracker,
+    output: &mut OutputManager,
 ) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/Scores_{}.csv", output_directory, seed);
+    let writer = output.writer(OutputKind::EvoStats)?;
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+    let mut strat_stats = vec![i.to_string()];
+    // Strategy pairs are shares of the games played, refusals and rejections
+    // shares of the visits attempted.
+    let games = interaction_tracker.games.max(1) as f64;
+    let visitors = interaction_tracker.visitors.max(1) as f64;
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+    let hawk_hawk = (interaction_tracker.hawk_hawk as f64 / games).to_string();
+    strat_stats.push(hawk_hawk);
+    let hawk_dove = (interaction_tracker.hawk_dove as f64 / games).to_string();
+    strat_stats.push(hawk_dove);
+    let dove_hawk = (interaction_tracker.dove_hawk as f64 / games).to_string();
+    strat_stats.push(dove_hawk);
+    let dove_dove = (interaction_tracker.dove_dove as f64 / games).to_string();
+    strat_stats.push(dove_dove);
+    let refused = (interaction_tracker.refused as f64 / visitors).to_string();
+    strat_stats.push(refused);
+    let rejected = (interaction_tracker.rejected as f64 / visitors).to_string();
+    strat_stats.push(rejected);
 
-    let mut scores_vector: Vec<f64> = Vec::new();
+    writer.write_record(&strat_stats)?;
 
-    for i in 0..agents.len() {
-        scores_vector.push(agents[i].score);
-    }
+    Ok(())
+}
 
-    let string_vec: Vec<String> = scores_vector.iter().map(|x| *x as f32).map(|x| x.to_string()).collect();
+pub fn generate_strategyvisit_csv(
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    output.write_agents(OutputKind::StrategyVisit, i, agents, |_, agent| {
+        let sum = agent.strategy.visit[0] + agent.strategy.visit[1];
+        agent.is_active().then(|| {
+            vec![
+                (agent.strategy.visit[0] / sum) as f32,
+                (agent.strategy.visit[1] / sum) as f32,
+            ]
+        })
+    })
+}
 
-    writer.write_record(string_vec)?;
+pub fn generate_strategyhost_csv(
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    output.write_agents(OutputKind::StrategyHost, i, agents, |_, agent| {
+        let sum = agent.strategy.host[0] + agent.strategy.host[1];
+        agent.is_active().then(|| {
+            vec![
+                (agent.strategy.host[0] / sum) as f32,
+                (agent.strategy.host[1] / sum) as f32,
+            ]
+        })
+    })
+}
 
-    Ok(())
+pub fn generate_scores_csv(
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    output.write_agents(OutputKind::Scores, i, agents, |_, agent| {
+        agent.is_active().then(|| vec![agent.score as f32])
+    })
 }
 
 pub fn generate_netstd_csv(
-    pop: usize,
+    i: u64,
+    agents: &[Agent],
     network: &Network,
-    output_directory: &str,
-    seed: u64,
+    output: &mut OutputManager,
 ) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/NetSTD_{}.csv", output_directory, seed);
-
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
-
-    let mut writer = WriterBuilder::new().from_writer(file);
-
+    let pop = agents.len();
     let mut column_sums: Vec<f64> = vec![0.0; pop];
 
     for i in 0..pop {
@@ -342,33 +2457,26 @@ pub fn generate_netstd_csv(
         }
     }
 
-    let string_vec: Vec<String> = column_sums.iter().map(|x| *x as f32).map(|x| x.to_string()).collect();
-
-    writer.write_record(string_vec)?;
-
-    Ok(())
+    output.write_agents(OutputKind::NetStd, i, agents, |id, agent| {
+        agent.is_active().then(|| vec![column_sums[id] as f32])
+    })
 }
 
 pub fn generate_outscore_csv(
-    pop: usize,
-    agents: &Vec<Agent>,
-    output_directory: &str,
-    seed: u64,
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
 ) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/OutScore_{}.csv", output_directory, seed);
-
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
-
-    let mut writer = WriterBuilder::new().from_writer(file);
     let mut scores: Vec<f64> = Vec::new();
-    for i in 0..pop {
-        scores.push(agents[i].score);
+    for agent in agents {
+        scores.push(agent.score);
     }
 
-    let mut indexed_data: Vec<(usize, &f64)> = scores.iter().enumerate().collect();
+    let mut indexed_data: Vec<(usize, &f64)> = scores
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| agents[index].is_active())
+        .collect();
     indexed_data.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap_or(std::cmp::Ordering::Equal));
 
     let mut ranks: HashMap<usize, usize> = HashMap::new();
@@ -390,132 +2498,462 @@ pub fn generate_outscore_csv(
         }
     }
 
-    let string_vec: Vec<String> = result.iter().map(|x| x.to_string()).collect();
-
-    writer.write_record(string_vec)?;
+    output.write_agents(OutputKind::OutScore, i, agents, |id, agent| {
+        agent.is_active().then(|| vec![result[id]])
+    })
+}
 
-    Ok(())
+pub fn generate_energy_csv(
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    output.write_agents(OutputKind::Energy, i, agents, |_, agent| {
+        agent.is_active().then(|| vec![agent.energy as f32])
+    })
 }
 
 pub fn generate_totalpayoff_csv(
-    pop: usize,
-    agents: &Vec<Agent>,
-    output_directory: &str,
-    seed: u64,
+    i: u64,
+    agents: &[Agent],
+    output: &mut OutputManager,
 ) -> Result<(), Box<dyn Error>> {
-    let filepath = format!("./Output/{}/TotalPayoff_{}.csv", output_directory, seed);
+    output.write_agents(OutputKind::TotalPayoff, i, agents, |_, agent| {
+        agent.is_active().then(|| vec![agent.total_payoff as f32])
+    })
+}
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+pub fn generate_totalinteractions_csv(
+    i: u64,
+    agents: &[Agent],
+    agent_interaction_tracker: &[AgentInteractionTracker],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    output.write_agents(OutputKind::TotalInteractions, i, agents, |id, agent| {
+        let tracker = &agent_interaction_tracker[id];
+        agent.is_active().then(|| {
+            vec![
+                tracker.hawk_hawk,
+                tracker.hawk_dove,
+                tracker.dove_hawk,
+                tracker.dove_dove,
+            ]
+        })
+    })
+}
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+pub fn generate_dyads_csv(
+    i: u64,
+    dyad_tracker: &DyadTracker,
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    let writer = output.writer(OutputKind::Dyads)?;
+
+    // One row per visitor-host pair that has interacted at least once:
+    // step, visitor, host, hh, hd, dh, dd, refused, visitor payoff sum, host payoff sum.
+    for (visitor, row) in dyad_tracker.0.iter().enumerate() {
+        for (host, dyad) in row.iter().enumerate() {
+            if dyad.total() == 0 {
+                continue;
+            }
+
+            writer.write_record(&[
+                i.to_string(),
+                visitor.to_string(),
+                host.to_string(),
+                dyad.hawk_hawk.to_string(),
+                dyad.hawk_dove.to_string(),
+                dyad.dove_hawk.to_string(),
+                dyad.dove_dove.to_string(),
+                dyad.refused.to_string(),
+                (dyad.visitor_payoff as f32).to_string(),
+                (dyad.host_payoff as f32).to_string(),
+            ])?;
+        }
+    }
 
-    let mut payoff_vector: Vec<f64> = Vec::new();
+    Ok(())
+}
 
-    for i in 0..pop {
-        payoff_vector.push(agents[i].total_payoff);
+pub fn generate_turnover_csv(
+    i: u64,
+    births: &[(AgentId, AgentId)],
+    agents: &[Agent],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    let writer = output.writer(OutputKind::Turnover)?;
+
+    // One row per replaced agent: step, agent, parent, generation, initial score.
+    for &(agent_id, parent_id) in births {
+        writer.write_record(&[
+            i.to_string(),
+            agent_id.0.to_string(),
+            parent_id.0.to_string(),
+            agents[agent_id.0 as usize].generation.to_string(),
+            (agents[agent_id.0 as usize].score as f32).to_string(),
+        ])?;
     }
 
-    let string_vec: Vec<String> = payoff_vector.iter().map(|x| *x as f32).map(|x| x.to_string()).collect();
+    Ok(())
+}
 
-    writer.write_record(string_vec)?;
+pub fn generate_population_csv(
+    i: u64,
+    changes: &[(AgentId, PopulationAction)],
+    output: &mut OutputManager,
+) -> Result<(), Box<dyn Error>> {
+    let writer = output.writer(OutputKind::Population)?;
+
+    // One row per agent joining or leaving: step, agent, action.
+    for &(agent_id, action) in changes {
+        writer.write_record(&[
+            i.to_string(),
+            agent_id.0.to_string(),
+            action.name().to_string(),
+        ])?;
+    }
 
     Ok(())
 }
 
-pub fn generate_totalinteractions_csv(
-    pop: usize,
-    agent_interaction_tracker: &Vec<AgentInteractionTracker>,
-    output_directory: &str,
-    seed: u64,
+pub fn run_track_vars(
+    i: u64,
+    agents: &Vec<Agent>,
+    network: &Network,
+    output: &mut OutputManager,
+    interaction_tracker: &InteractionTracker,
+    trackers: &Trackers,
+    config: &RootConfig,
 ) -> Result<(), Box<dyn Error>> {
-    let filepath = format!(
-        "./Output/{}/TotalInteractions_{}.csv",
-        output_directory, seed
-    );
+    let recording = &config.recording;
+    let max_time_step = config.simulation.max_time_step;
+    let records =
+        |enabled: bool, schedule: &RecordSchedule| enabled && schedule.records(i, max_time_step);
+
+    if records(config.csv.weights, &recording.weights) {
+        generate_weights_csv(i, agents, network, output)?;
+    }
 
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(&filepath)?;
+    if records(config.csv.scores, &recording.scores) {
+        generate_scores_csv(i, agents, output)?;
+    }
 
-    let mut writer = WriterBuilder::new().from_writer(file);
+    if records(config.csv.totalinteractions, &recording.totalinteractions) {
+        generate_totalinteractions_csv(i, agents, &trackers.agents, output)?;
+    }
 
-    let mut interaction_vector: Vec<u64> = Vec::new();
+    if let Some(dyad_tracker) = &trackers.dyads
+        && records(true, &recording.dyads)
+    {
+        generate_dyads_csv(i, dyad_tracker, output)?;
+    }
 
-    for i in 0..pop {
-        let hawk_hawk = agent_interaction_tracker[i].hawk_hawk;
-        interaction_vector.push(hawk_hawk);
-        let hawk_dove = agent_interaction_tracker[i].hawk_dove;
-        interaction_vector.push(hawk_dove);
-        let dove_hawk = agent_interaction_tracker[i].dove_hawk;
-        interaction_vector.push(dove_hawk);
-        let dove_dove = agent_interaction_tracker[i].dove_dove;
-        interaction_vector.push(dove_dove);
+    if records(config.csv.evostats, &recording.evostats) {
+        generate_evostats_csv(i, interaction_tracker, output)?;
     }
 
-    let string_vec: Vec<String> = interaction_vector.iter().map(|x| x.to_string()).collect();
+    if records(config.csv.strategyvisit, &recording.strategyvisit) {
+        generate_strategyvisit_csv(i, agents, output)?;
+    }
+
+    if records(config.csv.strategyhost, &recording.strategyhost) {
+        generate_strategyhost_csv(i, agents, output)?;
+    }
+
+    if records(config.csv.netstd, &recording.netstd) {
+        generate_netstd_csv(i, agents, network, output)?;
+    }
+
+    if records(config.csv.outscore, &recording.outscore) {
+        generate_outscore_csv(i, agents, output)?;
+    }
+
+    if records(config.csv.totalpayoff, &recording.totalpayoff) {
+        generate_totalpayoff_csv(i, agents, output)?;
+    }
 
-    writer.write_record(string_vec)?;
+    if records(config.csv.energy, &recording.energy) {
+        generate_energy_csv(i, agents, output)?;
+    }
 
     Ok(())
 }
 
-pub fn run_track_vars(
-    i: u64,
-    pop: usize,
-    agents: &Vec<Agent>,
-    network: &Network,
-    output_directory: &str,
-    seed: u64,
-    interaction_tracker: &InteractionTracker,
-    agent_interaction_tracker: &Vec<AgentInteractionTracker>,
-    config: &RootConfig,
-) {
-    let rounds = vec![
-        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 200, 400, 500, 600,
-        700, 800, 900, 1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000, 10000, 20000, 30000,
-        40000, 50000, 60000, 70000, 80000, 90000, 100000,
-    ];
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if rounds.contains(&i) {
-        if config.csv.weights {
-            let _ = generate_weights_csv(i, network, output_directory, seed);
+    fn axis(key: &str, values: SweepValues) -> SweepAxis {
+        SweepAxis {
+            key: key.to_string(),
+            values,
         }
+    }
+
+    fn expanded(values: SweepValues) -> Vec<String> {
+        axis("payoffs.hh_f", values)
+            .expand()
+            .unwrap()
+            .iter()
+            .map(|value| value.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn range_steps_without_float_drift() {
+        let values = expanded(SweepValues::Range {
+            start: 0.1,
+            stop: 1.0,
+            step: 0.1,
+        });
+        assert_eq!(
+            values,
+            [
+                "0.1", "0.2", "0.3", "0.4", "0.5", "0.6", "0.7", "0.8", "0.9", "1"
+            ]
+        );
+    }
 
-        if config.csv.scores {
-            let _ = generate_scores_csv(agents, output_directory, seed);
+    #[test]
+    fn range_stops_before_an_uneven_end() {
+        let values = expanded(SweepValues::Range {
+            start: 0.0,
+            stop: 1.0,
+            step: 0.3,
+        });
+        assert_eq!(values, ["0", "0.3", "0.6", "0.9"]);
+    }
+
+    #[test]
+    fn range_rejects_empty_or_backward_ranges() {
+        for (start, stop, step) in [(0.0, 1.0, 0.0), (0.0, 1.0, -0.1), (1.0, 0.0, 0.1)] {
+            let range = axis("payoffs.hh_f", SweepValues::Range { start, stop, step });
+            assert!(range.expand().is_err(), "{} {} {}", start, stop, step);
         }
+    }
+
+    #[test]
+    fn log_range_hits_both_bounds() {
+        let values = expanded(SweepValues::LogRange {
+            start: 1.0,
+            stop: 1000.0,
+            num: 4,
+        });
+        assert_eq!(values, ["1", "10", "100", "1000"]);
+
+        let single = expanded(SweepValues::LogRange {
+            start: 20.0,
+            stop: 500.0,
+            num: 1,
+        });
+        assert_eq!(single, ["20"]);
+    }
+
+    #[test]
+    fn log_range_rejects_non_positive_bounds() {
+        let range = axis(
+            "simulation.population",
+            SweepValues::LogRange {
+                start: 0.0,
+                stop: 100.0,
+                num: 3,
+            },
+        );
+        assert!(range.expand().is_err());
+    }
 
-        if config.csv.totalinteractions {
-            let _ =
-                generate_totalinteractions_csv(pop, agent_interaction_tracker, output_directory, seed);
+    fn sweep(mode: SweepMode, populations: Vec<i64>, hh_f: Vec<f64>) -> SweepParameters {
+        SweepParameters {
+            mode,
+            parameters: vec![
+                axis(
+                    "simulation.population",
+                    SweepValues::Values(populations.into_iter().map(SweepValue::Int).collect()),
+                ),
+                axis(
+                    "payoffs.hh_f",
+                    SweepValues::Values(hh_f.into_iter().map(SweepValue::Float).collect()),
+                ),
+            ],
         }
     }
-    
-    if config.csv.evostats {
-        let _ = generate_evostats_csv(i, pop, interaction_tracker, output_directory, seed);
+
+    fn labels(combos: &[Vec<(String, SweepValue)>]) -> Vec<String> {
+        combos
+            .iter()
+            .map(|combo| {
+                combo
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cartesian_varies_the_last_parameter_fastest() {
+        let combos = sweep(SweepMode::Cartesian, vec![20, 50], vec![0.1, 0.2])
+            .combinations()
+            .unwrap();
+        assert_eq!(
+            labels(&combos),
+            [
+                "simulation.population=20,payoffs.hh_f=0.1",
+                "simulation.population=20,payoffs.hh_f=0.2",
+                "simulation.population=50,payoffs.hh_f=0.1",
+                "simulation.population=50,payoffs.hh_f=0.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn zip_pairs_values_in_order() {
+        let combos = sweep(SweepMode::Zip, vec![20, 50], vec![0.1, 0.2])
+            .combinations()
+            .unwrap();
+        assert_eq!(
+            labels(&combos),
+            [
+                "simulation.population=20,payoffs.hh_f=0.1",
+                "simulation.population=50,payoffs.hh_f=0.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn zip_rejects_parameters_of_different_lengths() {
+        let sweep = sweep(SweepMode::Zip, vec![20, 50, 100], vec![0.1, 0.2]);
+        assert!(sweep.combinations().is_err());
+    }
+
+    fn recorded(schedule: RecordSchedule, max_time_step: u64) -> Vec<u64> {
+        (0..=max_time_step)
+            .filter(|&step| schedule.records(step, max_time_step))
+            .collect()
+    }
+
+    #[test]
+    fn every_includes_the_initial_state() {
+        assert_eq!(
+            recorded(RecordSchedule::Every(25), 100),
+            [0, 25, 50, 75, 100]
+        );
+    }
+
+    #[test]
+    fn log_records_once_per_decade_up_to_powers_of_ten() {
+        assert_eq!(
+            recorded(RecordSchedule::Log(1), 100000),
+            [1, 10, 100, 1000, 10000, 100000]
+        );
+    }
+
+    #[test]
+    fn log_rounds_to_the_nearest_step() {
+        // 10^(1/3) = 2.15, 10^(2/3) = 4.64, 10^(4/3) = 21.5, 10^(5/3) = 46.4
+        assert_eq!(
+            recorded(RecordSchedule::Log(3), 100),
+            [1, 2, 5, 10, 22, 46, 100]
+        );
+    }
+
+    #[test]
+    fn log_keeps_every_step_when_denser_than_the_steps() {
+        assert_eq!(
+            recorded(RecordSchedule::Log(20), 10),
+            (1..=10).collect::<Vec<_>>()
+        );
     }
 
-    if config.csv.strategyvisit {
-        let _ = generate_strategyvisit_csv(agents, output_directory, seed);
+    #[test]
+    fn steps_and_final() {
+        assert_eq!(recorded(RecordSchedule::Steps(vec![3, 7, 50]), 10), [3, 7]);
+        assert_eq!(recorded(RecordSchedule::Final, 10), [10]);
     }
 
-    if config.csv.strategyhost {
-        let _ = generate_strategyhost_csv(agents, output_directory, seed);
+    fn config_issues(name: &str, toml: &str) -> Vec<String> {
+        let input = std::env::temp_dir().join(format!("thesis-{}-{}", name, std::process::id()));
+        create_dir_all(&input).unwrap();
+        std::fs::write(input.join("config.toml"), toml).unwrap();
+        let paths = Paths {
+            input: input.clone(),
+            output: input.join("output"),
+        };
+        let result = read_config_file("config.toml", &paths, &Overrides::default());
+        remove_dir_all(&input).unwrap();
+        result
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|issue| issue.key)
+            .collect()
     }
 
-    if config.csv.netstd {
-        let _ = generate_netstd_csv(pop, network, output_directory, seed);
+    #[test]
+    fn reports_invalid_element_with_others_in_its_array() {
+        let issues = config_issues(
+            "invalid-element",
+            r#"
+            [simulation]
+            max_time_step = 10
+            [[population_events]]
+            step = 0
+            action = "immigrate"
+            count = 1
+            [[population_events]]
+            step = 5
+            action = "explode"
+            count = 1
+            "#,
+        );
+        assert_eq!(
+            issues,
+            ["population_events[0].step", "population_events[1].action"]
+        );
     }
 
-    if config.csv.outscore {
-        let _ = generate_outscore_csv(pop, agents, output_directory, seed);
+    #[test]
+    fn counts_elements_after_a_dropped_one_from_the_original_array() {
+        let issues = config_issues(
+            "dropped-element",
+            r#"
+            [simulation]
+            max_time_step = 10
+            [[population_events]]
+            step = 1
+            action = "immigrate"
+            [[population_events]]
+            step = 5
+            action = "explode"
+            count = 1
+            [[population_events]]
+            step = 0
+            action = "immigrate"
+            count = 1
+            "#,
+        );
+        assert_eq!(
+            issues,
+            [
+                "population_events[0].count",
+                "population_events[1].action",
+                "population_events[2].step"
+            ]
+        );
     }
 
-    if config.csv.totalpayoff {
-        let _ = generate_totalpayoff_csv(pop, agents, output_directory, seed);
+    #[test]
+    fn reports_missing_field_in_optional_section() {
+        let issues = config_issues(
+            "missing-field",
+            r#"
+            [simulation]
+            seeds = 0
+            [host]
+            outside_option = 0.5
+            "#,
+        );
+        assert_eq!(issues, ["host.rule", "simulation.seeds"]);
     }
 }